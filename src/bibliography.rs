@@ -0,0 +1,257 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with
+// this file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use crate::common;
+use crate::EpubVersion;
+
+use std::fmt;
+
+/// Path of the generated bibliography document
+pub(crate) const BIBLIOGRAPHY_FILE: &str = "bibliography.xhtml";
+
+/// A reference of the bibliography generated by [`EpubBuilder`](struct.EpubBuilder.html).
+///
+/// The key identifies the citation and is used as the id of its entry in the
+/// bibliography, so it must be a valid XML id.
+///
+/// # Example
+///
+/// ```
+/// use epub_builder::Citation;
+///
+/// let citation = Citation::new("knuth1984", "Literate Programming")
+///     .author("Donald E. Knuth")
+///     .year(1984)
+///     .doi("10.1093/comjnl/27.2.97");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Citation {
+    /// Key of the citation, used as the id of its entry
+    pub key: String,
+    /// Authors
+    pub authors: Vec<String>,
+    /// Title of the work
+    pub title: String,
+    /// Year of publication
+    pub year: Option<i32>,
+    /// Publisher
+    pub publisher: Option<String>,
+    /// URL where the work can be found
+    pub url: Option<String>,
+    /// Digital Object Identifier, e.g. `10.1093/comjnl/27.2.97`
+    pub doi: Option<String>,
+}
+
+impl Citation {
+    /// Creates a new citation
+    pub fn new<S1, S2>(key: S1, title: S2) -> Self
+    where
+        S1: Into<String>,
+        S2: Into<String>,
+    {
+        Citation {
+            key: key.into(),
+            authors: vec![],
+            title: title.into(),
+            year: None,
+            publisher: None,
+            url: None,
+            doi: None,
+        }
+    }
+
+    /// Adds an author
+    pub fn author<S: Into<String>>(mut self, author: S) -> Self {
+        self.authors.push(author.into());
+        self
+    }
+
+    /// Sets the year of publication
+    pub fn year(mut self, year: i32) -> Self {
+        self.year = Some(year);
+        self
+    }
+
+    /// Sets the publisher
+    pub fn publisher<S: Into<String>>(mut self, publisher: S) -> Self {
+        self.publisher = Some(publisher.into());
+        self
+    }
+
+    /// Sets the URL
+    pub fn url<S: Into<String>>(mut self, url: S) -> Self {
+        self.url = Some(url.into());
+        self
+    }
+
+    /// Sets the DOI
+    pub fn doi<S: Into<String>>(mut self, doi: S) -> Self {
+        self.doi = Some(doi.into());
+        self
+    }
+}
+
+/// Formats the entries of the bibliography.
+///
+/// The default formatter is [`DefaultCitationFormatter`](struct.DefaultCitationFormatter.html);
+/// another one can be set with the `bibliography_formatter` method of
+/// [`EpubBuilder`](struct.EpubBuilder.html) to match a house style.
+///
+/// # Example
+///
+/// ```
+/// use epub_builder::{Citation, CitationFormatter};
+///
+/// #[derive(Debug)]
+/// struct TitleOnly;
+///
+/// impl CitationFormatter for TitleOnly {
+///     fn format(&self, citation: &Citation, escape_html: bool) -> String {
+///         if escape_html {
+///             html_escape::encode_text(&citation.title).into_owned()
+///         } else {
+///             citation.title.clone()
+///         }
+///     }
+/// }
+/// ```
+pub trait CitationFormatter: fmt::Debug + Send + Sync {
+    /// Returns the XHTML content of the entry of a citation
+    ///
+    /// If `escape_html` is true, the fields of the citation must be escaped.
+    fn format(&self, citation: &Citation, escape_html: bool) -> String;
+
+    /// Returns the key used to sort the entries of the bibliography
+    ///
+    /// By default, entries are sorted by first author, year and title.
+    fn sort_key(&self, citation: &Citation) -> String {
+        format!(
+            "{}\u{0}{:08}\u{0}{}",
+            citation
+                .authors
+                .first()
+                .map(|author| author.to_lowercase())
+                .unwrap_or_default(),
+            citation.year.unwrap_or_default(),
+            citation.title.to_lowercase()
+        )
+    }
+}
+
+/// The default citation formatter, rendering entries as
+/// `Authors (Year). Title. Publisher. URL. doi:DOI`, with the title in a `<cite>`
+/// element and links for the URL and DOI.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DefaultCitationFormatter;
+
+impl CitationFormatter for DefaultCitationFormatter {
+    fn format(&self, citation: &Citation, escape_html: bool) -> String {
+        let mut parts = vec![];
+        let mut authors = citation
+            .authors
+            .iter()
+            .map(|author| common::encode_html(author, escape_html))
+            .collect::<Vec<_>>()
+            .join(", ");
+        if let Some(year) = citation.year {
+            if authors.is_empty() {
+                authors = year.to_string();
+            } else {
+                authors.push_str(&format!(" ({year})"));
+            }
+        }
+        if !authors.is_empty() {
+            parts.push(authors);
+        }
+        parts.push(format!(
+            "<cite>{}</cite>",
+            common::encode_html(&citation.title, escape_html)
+        ));
+        if let Some(ref publisher) = citation.publisher {
+            parts.push(common::encode_html(publisher, escape_html).into_owned());
+        }
+        if let Some(ref url) = citation.url {
+            parts.push(format!(
+                "<a href=\"{href}\">{text}</a>",
+                href = html_escape::encode_double_quoted_attribute(url),
+                text = html_escape::encode_text(url),
+            ));
+        }
+        if let Some(ref doi) = citation.doi {
+            parts.push(format!(
+                "doi:<a href=\"https://doi.org/{href}\">{text}</a>",
+                href = html_escape::encode_double_quoted_attribute(doi),
+                text = html_escape::encode_text(doi),
+            ));
+        }
+        parts.join(". ") + "."
+    }
+}
+
+/// Renders the body of the bibliography document, with `title`, which must already be escaped
+pub(crate) fn render_bibliography(
+    version: EpubVersion,
+    title: &str,
+    citations: &[Citation],
+    formatter: &dyn CitationFormatter,
+    escape_html: bool,
+) -> String {
+    let (element, semantics, entry_semantics) = match version {
+        EpubVersion::V20 => ("div", "class=\"bibliography\"", "class=\"biblioentry\""),
+        EpubVersion::V30 => (
+            "section",
+            "epub:type=\"bibliography\" role=\"doc-bibliography\"",
+            "epub:type=\"biblioentry\" role=\"doc-biblioentry\"",
+        ),
+    };
+    let mut sorted: Vec<&Citation> = citations.iter().collect();
+    sorted.sort_by_cached_key(|citation| formatter.sort_key(citation));
+    let items: Vec<String> = sorted
+        .into_iter()
+        .map(|citation| {
+            format!(
+                "<li {entry_semantics} id=\"{id}\">{content}</li>",
+                id = html_escape::encode_double_quoted_attribute(&citation.key),
+                content = formatter.format(citation, escape_html), // Not escaped: XML content
+            )
+        })
+        .collect();
+    format!(
+        "<{element} {semantics}>\n  <h1>{title}</h1>\n  <ul>\n{items}\n  </ul>\n</{element}>",
+        items = common::indent(items.join("\n"), 2), // Not escaped: XML content
+    )
+}
+
+#[test]
+fn bibliography_markup() {
+    let citations = vec![
+        Citation::new("b", "Second & last").author("Zoe").year(2001),
+        Citation::new("a", "First")
+            .author("Ann")
+            .author("Bob")
+            .year(1999)
+            .publisher("P")
+            .url("https://example.org/?a=1&b=2")
+            .doi("10.1000/182"),
+    ];
+    assert_eq!(
+        render_bibliography(
+            EpubVersion::V30,
+            "Bibliography",
+            &citations,
+            &DefaultCitationFormatter,
+            true
+        ),
+        "<section epub:type=\"bibliography\" role=\"doc-bibliography\">
+  <h1>Bibliography</h1>
+  <ul>
+    <li epub:type=\"biblioentry\" role=\"doc-biblioentry\" id=\"a\">Ann, Bob (1999). \
+<cite>First</cite>. P. <a href=\"https://example.org/?a=1&amp;b=2\">https://example.org/?a=1&amp;b=2</a>. \
+doi:<a href=\"https://doi.org/10.1000/182\">10.1000/182</a>.</li>
+    <li epub:type=\"biblioentry\" role=\"doc-biblioentry\" id=\"b\">Zoe (2001). \
+<cite>Second &amp; last</cite>.</li>
+  </ul>
+</section>"
+    );
+}
@@ -3,6 +3,7 @@
 // this file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
 use std::borrow::Cow;
+use std::path::Path;
 
 /// Indent lines of the string
 pub fn indent<S: AsRef<str>>(s: S, level: usize) -> String {
@@ -28,6 +29,169 @@ pub fn encode_html(s: &str, encode: bool) -> Cow<'_, str> {
     }
 }
 
+/// Normalizes the path of a file in the EPUB, relative to the `OEBPS` directory
+///
+/// Backslashes are replaced by slashes, and `.` and `..` components are resolved;
+/// an error is returned if the path is empty or not inside the EPUB.
+pub fn normalize_path(path: &Path) -> crate::Result<String> {
+    let original = format!("{}", path.display());
+    let invalid = |reason: &str| crate::Error::InvalidPath(format!("{original}: {reason}"));
+    let unified = original.replace('\\', "/");
+    if unified.starts_with('/') {
+        return Err(invalid("absolute paths are not allowed"));
+    }
+    let mut components: Vec<&str> = vec![];
+    for component in unified.split('/') {
+        match component {
+            "" | "." => (),
+            ".." => {
+                if components.pop().is_none() {
+                    return Err(invalid("path is outside the EPUB directory"));
+                }
+            }
+            component => components.push(component),
+        }
+    }
+    if components.is_empty() {
+        return Err(invalid("path is empty"));
+    }
+    Ok(components.join("/"))
+}
+
+/// Normalizes the path part of a link, leaving its fragment and absolute URLs untouched
+pub fn normalize_url(url: &str) -> crate::Result<String> {
+    let (base, fragment) = match url.split_once('#') {
+        Some((base, fragment)) => (base, Some(fragment)),
+        None => (url, None),
+    };
+    if base.is_empty() || base.contains(':') {
+        return Ok(url.to_string());
+    }
+    let mut normalized = normalize_path(Path::new(base))?;
+    if let Some(fragment) = fragment {
+        normalized.push('#');
+        normalized.push_str(fragment);
+    }
+    Ok(normalized)
+}
+
+/// Returns the href of `to` relative to `from`, both being paths relative to the
+/// `OEBPS` directory
+pub fn relative_href(from: &str, to: &str) -> String {
+    let from: Vec<&str> = from.split('/').collect();
+    let to: Vec<&str> = to.split('/').collect();
+    let from_dir = &from[..from.len() - 1];
+    let common = from_dir
+        .iter()
+        .zip(&to)
+        .take_while(|(a, b)| a == b)
+        .count()
+        .min(to.len() - 1);
+    let mut href = "../".repeat(from_dir.len() - common);
+    href.push_str(&to[common..].join("/"));
+    href
+}
+
+/// Percent-encodes the path of a file in the archive so it can be used as an href
+///
+/// Only the characters that are not allowed in an URL path are encoded. As the path is a
+/// literal file name, `%` is always encoded.
+pub fn encode_href(path: &str) -> Cow<'_, str> {
+    percent_encode(path, false)
+}
+
+/// Percent-encodes a link given by the user, keeping the `#` separating its fragment
+///
+/// Unlike `encode_href`, existing percent-encoded sequences are kept as is, since the
+/// link may already be encoded.
+pub fn encode_url(url: &str) -> Cow<'_, str> {
+    match url.split_once('#') {
+        Some((base, fragment)) => Cow::Owned(format!(
+            "{}#{}",
+            percent_encode(base, true),
+            percent_encode(fragment, true)
+        )),
+        None => percent_encode(url, true),
+    }
+}
+
+/// Percent-encodes the characters not allowed in an URL path, keeping existing
+/// percent-encoded sequences if `keep_escapes` is true
+fn percent_encode(s: &str, keep_escapes: bool) -> Cow<'_, str> {
+    let bytes = s.as_bytes();
+    let is_escape = |i: usize| {
+        keep_escapes
+            && bytes[i] == b'%'
+            && i + 2 < bytes.len()
+            && bytes[i + 1].is_ascii_hexdigit()
+            && bytes[i + 2].is_ascii_hexdigit()
+    };
+    let allowed = |i: usize| {
+        let c = bytes[i];
+        c.is_ascii_alphanumeric() || b"-._~!$&'()*+,;=:@/".contains(&c) || is_escape(i)
+    };
+    if (0..bytes.len()).all(allowed) {
+        return Cow::Borrowed(s);
+    }
+    let mut encoded = String::with_capacity(bytes.len());
+    for (i, c) in bytes.iter().enumerate() {
+        if allowed(i) {
+            encoded.push(*c as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", c));
+        }
+    }
+    Cow::Owned(encoded)
+}
+
+#[test]
+fn test_normalize_path() {
+    assert_eq!(
+        normalize_path(Path::new("a/./b/../c.xhtml")).unwrap(),
+        "a/c.xhtml"
+    );
+    assert_eq!(normalize_path(Path::new("a\\b.png")).unwrap(), "a/b.png");
+    assert_eq!(normalize_path(Path::new("./a//b.png")).unwrap(), "a/b.png");
+    assert!(normalize_path(Path::new("../a.png")).is_err());
+    assert!(normalize_path(Path::new("/a.png")).is_err());
+    assert!(normalize_path(Path::new("a/..")).is_err());
+    assert_eq!(normalize_url("./a.xhtml#b").unwrap(), "a.xhtml#b");
+    assert_eq!(normalize_url("#b").unwrap(), "#b");
+    assert_eq!(
+        normalize_url("https://example.org/../a").unwrap(),
+        "https://example.org/../a"
+    );
+}
+
+#[test]
+fn test_relative_href() {
+    assert_eq!(relative_href("chapter.xhtml", "notes.xhtml"), "notes.xhtml");
+    assert_eq!(
+        relative_href("text/a.xhtml", "stylesheet.css"),
+        "../stylesheet.css"
+    );
+    assert_eq!(relative_href("text/a.xhtml", "text/b.xhtml"), "b.xhtml");
+    assert_eq!(relative_href("a/b/c.xhtml", "a/d/e.png"), "../d/e.png");
+    assert_eq!(relative_href("notes.xhtml", "text/a.xhtml"), "text/a.xhtml");
+}
+
+#[test]
+fn test_encode_href() {
+    assert_eq!(encode_href("images/logo.png"), "images/logo.png");
+    assert_eq!(encode_href("my chapter.xhtml"), "my%20chapter.xhtml");
+    assert_eq!(encode_href("my%20chapter.xhtml"), "my%2520chapter.xhtml");
+    assert_eq!(encode_href("100%.xhtml"), "100%25.xhtml");
+    assert_eq!(encode_href("été#1.xhtml"), "%C3%A9t%C3%A9%231.xhtml");
+    assert_eq!(
+        encode_url("été.xhtml#note 1"),
+        "%C3%A9t%C3%A9.xhtml#note%201"
+    );
+    assert_eq!(
+        encode_url("my%20chapter.xhtml#a%20b"),
+        "my%20chapter.xhtml#a%20b"
+    );
+}
+
 #[test]
 #[allow(clippy::disallowed_names)]
 fn test_indent() {
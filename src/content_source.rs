@@ -0,0 +1,128 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with
+// this file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::io::Read;
+use std::path::PathBuf;
+
+/// Where the content of a file added to the EPUB comes from.
+///
+/// Sources are only read when the EPUB is generated, so files don't have to be ready
+/// (or kept in memory) when they are added to the [`EpubBuilder`](struct.EpubBuilder.html).
+///
+/// # Example
+///
+/// ```
+/// use epub_builder::{ContentSource, EpubContent};
+///
+/// // Content already in memory
+/// let intro = EpubContent::new("intro.xhtml", ContentSource::bytes("Some XHTML content"));
+/// // A file that will be opened when the EPUB is generated
+/// let chapter = EpubContent::new("chapter_1.xhtml", ContentSource::file("chapter_1.xhtml"));
+/// // Content produced by a closure, only called when the EPUB is generated
+/// let chapter_2 = EpubContent::new("chapter_2.xhtml",
+///                                  ContentSource::reader(|| Ok("Generated content".as_bytes())));
+/// ```
+pub enum ContentSource {
+    /// Content already in memory
+    Bytes(Vec<u8>),
+    /// Path of a file to read
+    File(PathBuf),
+    /// A closure returning a reader to the content
+    Reader(Box<dyn Fn() -> io::Result<Box<dyn Read>> + Send>),
+}
+
+impl ContentSource {
+    /// Creates a source from content already in memory
+    pub fn bytes<B: Into<Vec<u8>>>(bytes: B) -> Self {
+        ContentSource::Bytes(bytes.into())
+    }
+
+    /// Creates a source reading the file at `path`
+    pub fn file<P: Into<PathBuf>>(path: P) -> Self {
+        ContentSource::File(path.into())
+    }
+
+    /// Creates a source from a closure returning a reader
+    ///
+    /// The closure is called each time the content needs to be read.
+    pub fn reader<F, R>(f: F) -> Self
+    where
+        F: Fn() -> io::Result<R> + Send + 'static,
+        R: Read + 'static,
+    {
+        ContentSource::Reader(Box::new(move || f().map(|r| Box::new(r) as Box<dyn Read>)))
+    }
+
+    /// Opens a reader to the content
+    pub fn open(&self) -> io::Result<Box<dyn Read + '_>> {
+        match self {
+            ContentSource::Bytes(bytes) => Ok(Box::new(bytes.as_slice())),
+            ContentSource::File(path) => Ok(Box::new(File::open(path)?)),
+            ContentSource::Reader(f) => f(),
+        }
+    }
+
+    /// Reads all the content in memory
+    pub fn read_to_vec(&self) -> io::Result<Vec<u8>> {
+        match self {
+            ContentSource::Bytes(bytes) => Ok(bytes.clone()),
+            _ => {
+                let mut bytes = vec![];
+                self.open()?.read_to_end(&mut bytes)?;
+                Ok(bytes)
+            }
+        }
+    }
+}
+
+impl fmt::Debug for ContentSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ContentSource::Bytes(bytes) => write!(f, "Bytes({} bytes)", bytes.len()),
+            ContentSource::File(path) => write!(f, "File({:?})", path),
+            ContentSource::Reader(_) => write!(f, "Reader"),
+        }
+    }
+}
+
+/// Conversion into a [`ContentSource`].
+///
+/// This is implemented for `ContentSource` itself, and for all readers: they are read
+/// in memory immediately, since they can't be kept until the EPUB is generated. For
+/// large files, prefer `ContentSource::file` or `ContentSource::reader`.
+pub trait IntoContentSource {
+    /// Converts to a `ContentSource`
+    fn into_content_source(self) -> io::Result<ContentSource>;
+}
+
+impl IntoContentSource for ContentSource {
+    fn into_content_source(self) -> io::Result<ContentSource> {
+        Ok(self)
+    }
+}
+
+impl<R: Read> IntoContentSource for R {
+    fn into_content_source(mut self) -> io::Result<ContentSource> {
+        let mut bytes = vec![];
+        self.read_to_end(&mut bytes)?;
+        Ok(ContentSource::Bytes(bytes))
+    }
+}
+
+#[test]
+fn content_source_read() {
+    let source = "Some content".as_bytes().into_content_source().unwrap();
+    assert_eq!(source.read_to_vec().unwrap(), b"Some content");
+
+    let source = ContentSource::reader(|| Ok("Some content".as_bytes()));
+    assert_eq!(source.read_to_vec().unwrap(), b"Some content");
+    // Can be read several times
+    assert_eq!(source.read_to_vec().unwrap(), b"Some content");
+
+    let source = ContentSource::file("does/not/exist.xhtml");
+    assert!(source.read_to_vec().is_err());
+}
@@ -2,15 +2,27 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with
 // this file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
+use crate::bibliography::{self, Citation, CitationFormatter, DefaultCitationFormatter};
+use crate::content_source::IntoContentSource;
+use crate::glossary::{self, GlossaryEntry};
+use crate::index::{self, IndexEntry};
+use crate::labels::Label;
+use crate::notes::{self, Note, NoteStyle};
+use crate::subject::{self, Subject};
 use crate::templates;
+use crate::title::{self, Title, TitleType};
 use crate::toc::{Toc, TocElement};
 use crate::zip::Zip;
+use crate::Compression;
+use crate::ContentSource;
 use crate::ReferenceType;
 use crate::Result;
 use crate::{common, EpubContent};
 
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
 use std::io;
-use std::io::Read;
 use std::path::Path;
 use std::str::FromStr;
 use upon::Engine;
@@ -60,11 +72,118 @@ impl MetadataOpf {
     }
 }
 
-impl ToString for PageDirection {
-    fn to_string(&self) -> String {
+/// Options for a resource added with the `add_resource_with_options` method of
+/// [`EpubBuilder`](struct.EpubBuilder.html).
+///
+/// # Example
+///
+/// ```
+/// use epub_builder::{Compression, ResourceOptions};
+///
+/// // JPEG images are already compressed
+/// let options = ResourceOptions::new().compression(Compression::Stored);
+/// ```
+#[non_exhaustive]
+#[derive(Debug, Clone, Default)]
+pub struct ResourceOptions {
+    /// Compression of this file, overriding the default one of the zip implementation
+    pub compression: Option<Compression>,
+    /// Id of this file in the manifest; if `None`, it is derived from its path
+    pub id: Option<String>,
+}
+
+impl ResourceOptions {
+    /// Creates default options
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the compression of this file
+    pub fn compression(mut self, compression: Compression) -> Self {
+        self.compression = Some(compression);
+        self
+    }
+
+    /// Sets the id of this file in the manifest, which must be a valid XML id and
+    /// be unique in the EPUB. Ids starting with `epub-` are reserved for metadata.
+    pub fn id<S: Into<String>>(mut self, id: S) -> Self {
+        self.id = Some(id.into());
+        self
+    }
+}
+
+/// A page that the builder can generate from the metadata of the book, see
+/// [`EpubBuilder::generate_page`](struct.EpubBuilder.html#method.generate_page).
+#[non_exhaustive]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum GeneratedPage {
+    /// Title page, with the title, authors and publisher, written in `title_page.xhtml`
+    TitlePage,
+    /// Copyright page, with the authors, license, publisher and identifier, written in
+    /// `copyright.xhtml`
+    Copyright,
+    /// Colophon, with the generator and date of the book, written in `colophon.xhtml`
+    Colophon,
+}
+
+impl GeneratedPage {
+    /// Path of the generated file
+    fn file(self) -> &'static str {
+        match self {
+            GeneratedPage::TitlePage => "title_page.xhtml",
+            GeneratedPage::Copyright => "copyright.xhtml",
+            GeneratedPage::Colophon => "colophon.xhtml",
+        }
+    }
+
+    /// Title of the page, for the guide and landmarks
+    fn label(self) -> Label {
+        match self {
+            GeneratedPage::TitlePage => Label::TitlePage,
+            GeneratedPage::Copyright => Label::Copyright,
+            GeneratedPage::Colophon => Label::Colophon,
+        }
+    }
+
+    /// Reference type of the page
+    fn reftype(self) -> ReferenceType {
+        match self {
+            GeneratedPage::TitlePage => ReferenceType::TitlePage,
+            GeneratedPage::Copyright => ReferenceType::Copyright,
+            GeneratedPage::Colophon => ReferenceType::Colophon,
+        }
+    }
+}
+
+/// Where a generated page is inserted in the spine
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SpinePosition {
+    /// Before the content that was added (pages at the start keep the order in which they
+    /// were requested)
+    Start,
+    /// After all the content
+    End,
+    /// Just before the file at this href
+    Before(String),
+    /// Just after the file at this href
+    After(String),
+}
+
+/// Summary of the resources that were deduplicated, see
+/// [`EpubBuilder::deduplicate_resources`](struct.EpubBuilder.html#method.deduplicate_resources).
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct DeduplicationReport {
+    /// Number of resources that were not stored because they were identical to another one
+    pub duplicates: usize,
+    /// Number of bytes that were not stored
+    pub bytes_saved: u64,
+}
+
+impl std::fmt::Display for PageDirection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match &self {
-            PageDirection::Rtl => "rtl".into(),
-            PageDirection::Ltr => "ltr".into(),
+            PageDirection::Rtl => write!(f, "rtl"),
+            PageDirection::Ltr => write!(f, "ltr"),
         }
     }
 }
@@ -86,14 +205,20 @@ impl FromStr for PageDirection {
 #[derive(Debug)]
 pub struct Metadata {
     pub title: String,
+    /// Titles added with `add_title`
+    pub titles: Vec<Title>,
     pub author: Vec<String>,
     pub lang: String,
     pub direction: PageDirection,
     pub generator: String,
-    pub toc_name: String,
     pub description: Vec<String>,
+    /// Descriptions added with `add_description_with_lang`, as `(text, lang)`
+    pub localized_descriptions: Vec<(String, String)>,
     pub subject: Vec<String>,
+    /// Subjects added with `add_structured_subject`
+    pub structured_subjects: Vec<Subject>,
     pub license: Option<String>,
+    pub publisher: Option<String>,
     pub date_published: Option<chrono::DateTime<chrono::Utc>>,
     pub date_modified: Option<chrono::DateTime<chrono::Utc>>,
     pub uuid: Option<uuid::Uuid>,
@@ -103,14 +228,17 @@ impl Default for Metadata {
     fn default() -> Self {
         Self {
             title: String::new(),
+            titles: vec![],
             author: vec![],
             lang: String::from("en"),
             direction: PageDirection::default(),
             generator: String::from("Rust EPUB library"),
-            toc_name: String::from("Table Of Contents"),
             description: vec![],
+            localized_descriptions: vec![],
             subject: vec![],
+            structured_subjects: vec![],
             license: None,
+            publisher: None,
             date_published: None,
             date_modified: None,
             uuid: None,
@@ -127,6 +255,30 @@ struct Content {
     pub cover: bool,
     pub reftype: Option<ReferenceType>,
     pub title: String,
+    /// Where to read the content from, `None` for files generated by the builder
+    pub source: Option<ContentSource>,
+    pub compression: Option<Compression>,
+    /// Entry of this file in the table of contents, if any
+    pub toc: Option<TocElement>,
+    /// Id in the manifest, set when the file is added to the builder
+    pub id: String,
+    /// Whether the content is a body fragment, to wrap in a page when it is written
+    pub fragment: bool,
+    /// Title of the page, for fragments
+    pub page_title: String,
+    /// Whether the file is part of the linear reading order, for spine items
+    pub linear: bool,
+    /// Whether the content embeds SVG
+    pub svg: bool,
+    /// Whether the content contains scripts
+    pub scripted: bool,
+    /// Whether the XML declaration and the doctype must be written before the content
+    pub xml_prolog: bool,
+    /// Whether this is the stylesheet set with `stylesheet`, which is never deduplicated
+    /// since generated pages link to it
+    pub stylesheet: bool,
+    /// Additional manifest property of generated files, in EPUB 3
+    pub property: Option<&'static str>,
 }
 
 impl Content {
@@ -143,6 +295,18 @@ impl Content {
             cover: false,
             reftype: None,
             title: String::new(),
+            source: None,
+            compression: None,
+            toc: None,
+            id: String::new(),
+            fragment: false,
+            page_title: String::new(),
+            linear: true,
+            svg: false,
+            scripted: false,
+            xml_prolog: false,
+            stylesheet: false,
+            property: None,
         }
     }
 }
@@ -167,7 +331,6 @@ impl Content {
 #[derive(Debug)]
 pub struct EpubBuilder<Z: Zip> {
     version: EpubVersion,
-    direction: PageDirection,    
     zip: Z,
     files: Vec<Content>,
     metadata: Metadata,
@@ -175,15 +338,37 @@ pub struct EpubBuilder<Z: Zip> {
     stylesheet: bool,
     inline_toc: bool,
     escape_html: bool,
+    reproducible: bool,
+    deduplicate: bool,
+    /// Hrefs of the deduplicated resources, by hash of their content
+    resource_hashes: HashMap<u64, Vec<String>>,
+    /// Canonical href of resources that were not stored because they were duplicates
+    aliases: HashMap<String, String>,
+    deduplication: DeduplicationReport,
+    page_template: Option<upon::Template<'static>>,
+    /// Whether to generate a cover page, and if it is linear
+    cover_page: Option<bool>,
+    generated_pages: Vec<(GeneratedPage, SpinePosition)>,
+    generated_page_templates: HashMap<GeneratedPage, upon::Template<'static>>,
+    notes: Vec<Note>,
+    note_style: NoteStyle,
+    glossary: Vec<GlossaryEntry>,
+    dictionary: Vec<GlossaryEntry>,
+    /// Language the terms of the dictionary are defined in, if not the language of the book
+    dictionary_target_language: Option<String>,
+    citations: Vec<Citation>,
+    citation_formatter: Box<dyn CitationFormatter>,
+    index: Vec<IndexEntry>,
+    labels: HashMap<Label, String>,
+    validate_subject_codes: bool,
     meta_opf: Vec<MetadataOpf>
 }
 
 impl<Z: Zip> EpubBuilder<Z> {
     /// Create a new default EPUB Builder
     pub fn new(zip: Z) -> Result<EpubBuilder<Z>> {
-        let mut epub = EpubBuilder {
+        let epub = EpubBuilder {
             version: EpubVersion::V20,
-            direction: PageDirection::Ltr,
             zip,
             files: vec![],
             metadata: Metadata::default(),
@@ -191,16 +376,28 @@ impl<Z: Zip> EpubBuilder<Z> {
             stylesheet: false,
             inline_toc: false,
             escape_html: true,
+            reproducible: false,
+            deduplicate: false,
+            resource_hashes: HashMap::new(),
+            aliases: HashMap::new(),
+            deduplication: DeduplicationReport::default(),
+            page_template: None,
+            cover_page: None,
+            generated_pages: vec![],
+            generated_page_templates: HashMap::new(),
+            notes: vec![],
+            note_style: NoteStyle::default(),
+            glossary: vec![],
+            dictionary: vec![],
+            dictionary_target_language: None,
+            citations: vec![],
+            citation_formatter: Box::new(DefaultCitationFormatter),
+            index: vec![],
+            labels: HashMap::new(),
+            validate_subject_codes: false,
             meta_opf: Vec::new()
         };
 
-        epub.zip
-            .write_file("META-INF/container.xml", templates::CONTAINER)?;
-        epub.zip.write_file(
-            "META-INF/com.apple.ibooks.display-options.xml",
-            templates::IBOOKS,
-        )?;
-
         Ok(epub)
     }
 
@@ -220,9 +417,10 @@ impl<Z: Zip> EpubBuilder<Z> {
     /// * `Ltr`: Left-To-Right 
     /// * `Rtl`: Right-To-Left 
     /// 
-    /// 
+    /// This is the same as setting the `direction` metadata: it sets the page progression
+    /// direction of the spine, and the direction of the generated documents.
     pub fn epub_direction(&mut self, direction: PageDirection) -> &mut Self {
-        self.direction = direction;
+        self.metadata.direction = direction;
         self
     }
     
@@ -238,7 +436,7 @@ impl<Z: Zip> EpubBuilder<Z> {
     /// use epub_builder::ZipCommand;
     /// use epub_builder::MetadataOpf;
     /// let mut builder = EpubBuilder::new(ZipCommand::new().unwrap()).unwrap();
-
+    ///
     /// builder.add_metadata_opf(MetadataOpf {
     ///     name: String::from("primary-writing-mode"),
     ///     content: String::from("vertical-rl")
@@ -260,14 +458,17 @@ impl<Z: Zip> EpubBuilder<Z> {
     ///
     /// * `author`: author(s) of the book;
     /// * `title`: title of the book;
+    /// * `subtitle`: subtitle of the book;
+    /// * `edition`: edition statement of the book, e.g. "Second edition";
     /// * `lang`: the language of the book, quite important as EPUB renderers rely on it
     ///   for e.g. hyphenating words.
     /// * `generator`: generator of the book (should be your program name);
-    /// * `toc_name`: the name to use for table of contents (by default, "Table of Contents");
+    /// * `toc_name`: the name to use for table of contents (by default, "Table Of Contents",
+    ///   translated according to `lang`);
     /// * `subject`;
     /// * `description`;
-    /// * `license`.
-
+    /// * `license`;
+    /// * `publisher`.
     pub fn metadata<S1, S2>(&mut self, key: S1, value: S2) -> Result<&mut Self>
     where
         S1: AsRef<str>,
@@ -283,6 +484,8 @@ impl<Z: Zip> EpubBuilder<Z> {
                 }
             }
             "title" => self.metadata.title = value.into(),
+            "subtitle" => self.set_subtitle(value),
+            "edition" => self.set_edition(value),
             "lang" => self.metadata.lang = value.into(),
             "direction" => self.metadata.direction = PageDirection::from_str(&value.into())?,
             "generator" => self.metadata.generator = value.into(),
@@ -303,7 +506,8 @@ impl<Z: Zip> EpubBuilder<Z> {
                 }
             }
             "license" => self.metadata.license = Some(value.into()),
-            "toc_name" => self.metadata.toc_name = value.into(),
+            "publisher" => self.metadata.publisher = Some(value.into()),
+            "toc_name" => self.set_toc_name(value),
             s => Err(crate::Error::InvalidMetadataError(s.to_string()))?,
         }
         Ok(self)
@@ -329,15 +533,170 @@ impl<Z: Zip> EpubBuilder<Z> {
         self.metadata.title = value.into();
     }
 
+    /// Sets the subtitle of the EPUB
+    ///
+    /// In EPUB 3, it is written as a separate title, of type `subtitle`. EPUB 2 has no
+    /// title types, so it is appended to the main title, e.g. `Title: Subtitle`.
+    pub fn set_subtitle<S: Into<String>>(&mut self, value: S) {
+        self.set_typed_title(TitleType::Subtitle, value.into());
+    }
+
+    /// Sets the edition statement of the EPUB, e.g. "Second edition"
+    ///
+    /// In EPUB 3, it is written as a separate title, of type `edition`. EPUB 2 has no
+    /// title types, so it is appended to the main title, e.g. `Title (Second edition)`.
+    pub fn set_edition<S: Into<String>>(&mut self, value: S) {
+        self.set_typed_title(TitleType::Edition, value.into());
+    }
+
+    /// Replaces the title of the given type in the language of the book
+    fn set_typed_title(&mut self, title_type: TitleType, value: String) {
+        self.metadata
+            .titles
+            .retain(|title| title.title_type != Some(title_type) || title.lang.is_some());
+        if !value.is_empty() {
+            self.add_title(Title::new(value).title_type(title_type));
+        }
+    }
+
+    /// Adds a title to the EPUB, e.g. in another language, or of another type
+    ///
+    /// Titles are written as `dc:title` elements, with their language and, in EPUB 3, their
+    /// type and display sequence. The title set with `set_title` (or the `title` metadata)
+    /// is the main title; if there is none, the builder chooses it among these titles (see
+    /// `main_title`).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use epub_builder::{EpubBuilder, ZipLibrary};
+    /// use epub_builder::{Title, TitleType};
+    /// # let mut builder = EpubBuilder::new(ZipLibrary::new().unwrap()).unwrap();
+    /// builder.set_lang("en");
+    /// builder
+    ///     .add_title(Title::new("The Little Prince").lang("en").title_type(TitleType::Main))
+    ///     .add_title(Title::new("Le Petit Prince").lang("fr").title_type(TitleType::Main));
+    /// assert_eq!(builder.main_title(), "The Little Prince");
+    /// ```
+    pub fn add_title(&mut self, title: Title) -> &mut Self {
+        self.metadata.titles.push(title);
+        self
+    }
+
+    /// Returns the main title of the book, used in the navigation documents and the NCX
+    ///
+    /// This is the title set with `set_title`, or else the first title added with
+    /// `add_title` whose type is `Main` (or unset) in the language of the book, or else the
+    /// first one whose type is `Main` (or unset), or else the first one.
+    pub fn main_title(&self) -> &str {
+        if !self.metadata.title.is_empty() {
+            return &self.metadata.title;
+        }
+        title::main_title(&self.metadata.titles, &self.metadata.lang)
+            .map(|title| title.text.as_str())
+            .unwrap_or_default()
+    }
+
+    /// Returns all the titles of the book, the main one first
+    fn titles(&self) -> Vec<Title> {
+        let mut titles = self.metadata.titles.clone();
+        match title::main_title(&self.metadata.titles, &self.metadata.lang) {
+            Some(main) if self.metadata.title.is_empty() => {
+                let index = titles.iter().position(|title| title == main).unwrap_or_default();
+                let main = titles.remove(index);
+                titles.insert(0, main);
+            }
+            // `dc:title` is required, even if it is empty, and the main title can't be a
+            // subtitle or an edition
+            _ => titles.insert(0, Title::new(self.metadata.title.as_str())),
+        }
+        titles
+    }
+
+    /// Title of the navigation documents and the NCX: the main title, or else the name of
+    /// the table of contents
+    fn doc_title(&self) -> &str {
+        match self.main_title() {
+            "" => self.label(Label::Toc),
+            title => title,
+        }
+    }
+
     /// Tells whether fields should be HTML-escaped.
     ///
     /// * `true`: fields such as titles, description, and so on will be HTML-escaped everywhere (default)
     /// * `false`: fields will be left as is (letting you in charge of making
-    /// sure they do not contain anything illegal, e.g. < and > characters)
+    ///   sure they do not contain anything illegal, e.g. < and > characters)
     pub fn escape_html(&mut self, val: bool) {
         self.escape_html = val;
     }
 
+    /// Tells whether the EPUB should be reproducible (default: false).
+    ///
+    /// When enabled, generating the same book twice gives byte-identical files:
+    ///
+    /// * the modification date (unless set with `set_modified_date`) and the timestamps of
+    ///   the files in the archive are taken from the `SOURCE_DATE_EPOCH` environment variable,
+    ///   or set to 1980-01-01 if it isn't defined;
+    /// * the identifier (unless set with `set_uuid`) is derived from the title and authors
+    ///   instead of being random;
+    /// * the files in the archive all get the same permissions.
+    pub fn reproducible(&mut self, val: bool) -> &mut Self {
+        self.reproducible = val;
+        self
+    }
+
+    /// Tells whether identical resources should only be stored once (default: false).
+    ///
+    /// When enabled, `add_resource` compares the content of each resource with the ones
+    /// that were already added: if a resource with the same content and mime type exists,
+    /// the new one is not stored, and links should point to the existing one, which is
+    /// given by `canonical_href`. Note that this requires reading the content of resources
+    /// when they are added, instead of when the EPUB is generated.
+    ///
+    /// Resources added with a different compression are stored separately, and so are
+    /// resources added with an explicit id (see `ResourceOptions`), so that this id is
+    /// always in the manifest.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use epub_builder::{EpubBuilder, ZipLibrary};
+    /// let mut builder = EpubBuilder::new(ZipLibrary::new().unwrap()).unwrap();
+    /// builder.deduplicate_resources(true);
+    /// let logo = "Should be a PNG image";
+    /// builder.add_resource("chapter_1/logo.png", logo.as_bytes(), "image/png").unwrap();
+    /// builder.add_resource("chapter_2/logo.png", logo.as_bytes(), "image/png").unwrap();
+    /// assert_eq!(builder.canonical_href("chapter_2/logo.png"), Some("chapter_1/logo.png"));
+    /// assert_eq!(builder.deduplication_report().bytes_saved, logo.len() as u64);
+    /// ```
+    pub fn deduplicate_resources(&mut self, val: bool) -> &mut Self {
+        self.deduplicate = val;
+        self
+    }
+
+    /// Returns the href under which the resource added at `path` is stored, or `None`
+    /// if no resource was added at this path.
+    ///
+    /// This is `path` itself, unless the resource was deduplicated (see
+    /// `deduplicate_resources`). Like all hrefs, it is relative to the `OEBPS` directory.
+    pub fn canonical_href<P: AsRef<Path>>(&self, path: P) -> Option<&str> {
+        let href = common::normalize_path(path.as_ref()).ok()?;
+        match self.aliases.get(&href) {
+            Some(canonical) => Some(canonical.as_str()),
+            None => self
+                .files
+                .iter()
+                .find(|file| file.file == href)
+                .map(|file| file.file.as_str()),
+        }
+    }
+
+    /// Returns how many resources were deduplicated, and how many bytes it saved
+    pub fn deduplication_report(&self) -> DeduplicationReport {
+        self.deduplication
+    }
+
     /// Sets the language of the EPUB
     ///
     /// This is quite important as EPUB renderers rely on it
@@ -351,9 +710,42 @@ impl<Z: Zip> EpubBuilder<Z> {
         self.metadata.generator = value.into();
     }
 
-    /// Sets the name to use for table of contents. This is by default, "Table of Contents"
+    /// Sets the name to use for table of contents. This is by default, "Table Of Contents",
+    /// translated according to the language of the book.
+    ///
+    /// This is the same as `set_label(Label::Toc, value)`.
     pub fn set_toc_name<S: Into<String>>(&mut self, value: S) {
-        self.metadata.toc_name = value.into();
+        self.set_label(Label::Toc, value);
+    }
+
+    /// Overrides a string written in the generated documents
+    ///
+    /// By default, these strings are translated according to the language of the book
+    /// (see [`Label`](enum.Label.html)).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use epub_builder::{EpubBuilder, ZipLibrary};
+    /// use epub_builder::Label;
+    /// # let mut builder = EpubBuilder::new(ZipLibrary::new().unwrap()).unwrap();
+    /// builder.set_lang("fr");
+    /// assert_eq!(builder.label(Label::Notes), "Notes");
+    /// builder.set_label(Label::Notes, "Notes de l'auteur");
+    /// assert_eq!(builder.label(Label::Notes), "Notes de l'auteur");
+    /// ```
+    pub fn set_label<S: Into<String>>(&mut self, label: Label, value: S) -> &mut Self {
+        self.labels.insert(label, value.into());
+        self
+    }
+
+    /// Returns a string written in the generated documents: either the one set with
+    /// `set_label`, or the default one for the language of the book
+    pub fn label(&self, label: Label) -> &str {
+        match self.labels.get(&label) {
+            Some(value) => value,
+            None => label.default_for(&self.metadata.lang),
+        }
     }
 
     /// Sets and replaces the description of the EPUB
@@ -366,9 +758,23 @@ impl<Z: Zip> EpubBuilder<Z> {
         self.metadata.description.push(value.into());
     }
 
+    /// Adds a line to the EPUB description, in the given language
+    ///
+    /// This allows to describe the book in several languages, e.g. for bilingual editions.
+    pub fn add_description_with_lang<S1, S2>(&mut self, value: S1, lang: S2)
+    where
+        S1: Into<String>,
+        S2: Into<String>,
+    {
+        self.metadata
+            .localized_descriptions
+            .push((value.into(), lang.into()));
+    }
+
     /// Remove all description paragraphs from EPUB
     pub fn clear_description(&mut self) {
         self.metadata.description.clear();
+        self.metadata.localized_descriptions.clear();
     }
 
     /// Sets and replaces the subjects of the EPUB
@@ -381,9 +787,39 @@ impl<Z: Zip> EpubBuilder<Z> {
         self.metadata.subject.push(value.into());
     }
 
+    /// Adds a subject, possibly with a code from a classification scheme such as BISAC,
+    /// Thema or LCSH
+    ///
+    /// If `validate_subject_codes` is enabled, generating the EPUB returns an error if the
+    /// code does not have the format of its authority.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use epub_builder::{EpubBuilder, ZipLibrary};
+    /// use epub_builder::{Subject, SubjectAuthority};
+    /// # let mut builder = EpubBuilder::new(ZipLibrary::new().unwrap()).unwrap();
+    /// builder
+    ///     .add_structured_subject(Subject::new("Fiction").code(SubjectAuthority::Bisac, "FIC"))
+    ///     .validate_subject_codes(true);
+    /// assert!(builder.generate(Vec::new()).is_err());
+    /// ```
+    pub fn add_structured_subject(&mut self, subject: Subject) -> &mut Self {
+        self.metadata.structured_subjects.push(subject);
+        self
+    }
+
+    /// Sets whether subject codes added with `add_structured_subject` are checked when the
+    /// EPUB is generated (default: false)
+    pub fn validate_subject_codes(&mut self, validate: bool) -> &mut Self {
+        self.validate_subject_codes = validate;
+        self
+    }
+
     /// Remove all the subjects from EPUB
     pub fn clear_subjects(&mut self) {
         self.metadata.subject.clear();
+        self.metadata.structured_subjects.clear();
     }
 
     /// Sets the license under which this EPUB is distributed
@@ -391,6 +827,11 @@ impl<Z: Zip> EpubBuilder<Z> {
         self.metadata.license = Some(value.into());
     }
 
+    /// Sets the publisher of the EPUB
+    pub fn set_publisher<S: Into<String>>(&mut self, value: S) {
+        self.metadata.publisher = Some(value.into());
+    }
+
     /// Sets the publication date of the EPUB
     pub fn set_publication_date(&mut self, date_published: chrono::DateTime<chrono::Utc>) {
         self.metadata.date_published = Some(date_published);
@@ -414,12 +855,61 @@ impl<Z: Zip> EpubBuilder<Z> {
     /// This content will be written in a `stylesheet.css` file; it is used by
     /// some pages (such as nav.xhtml), you don't have use it in your documents though it
     /// makes sense to also do so.
-    pub fn stylesheet<R: Read>(&mut self, content: R) -> Result<&mut Self> {
-        self.add_resource("stylesheet.css", content, "text/css")?;
+    pub fn stylesheet<R: IntoContentSource>(&mut self, content: R) -> Result<&mut Self> {
+        if self.stylesheet {
+            self.remove("stylesheet.css")?;
+        }
+        self.check_href("stylesheet.css")?;
+        let mut file = Content::new("stylesheet.css", "text/css");
+        file.source = Some(read_source(Path::new("stylesheet.css"), content)?);
+        file.stylesheet = true;
+        self.insert_file(self.files.len(), file)?;
         self.stylesheet = true;
         Ok(self)
     }
 
+    /// Sets the template used to wrap body fragments in a complete XHTML page
+    ///
+    /// Fragments are contents created with `EpubContent::from_fragment` (or from Markdown
+    /// or HTML). By default, the page is an XHTML 1.1 document for EPUB 2, and an HTML5
+    /// document for EPUB 3. The template uses the [upon](https://docs.rs/upon) syntax,
+    /// with the following values, which are already escaped:
+    ///
+    /// * `lang_attr`: the language of the book;
+    /// * `dir_attr`: `rtl` for right-to-left books, empty else;
+    /// * `generator_attr`: the generator of the book;
+    /// * `title`: the title of the content (or of the book if it has none);
+    /// * `stylesheet_attr`: the path of the stylesheet, relative to the page;
+    /// * `body`: the fragment.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use epub_builder::{EpubBuilder, ZipLibrary};
+    /// # let mut builder = EpubBuilder::new(ZipLibrary::new().unwrap()).unwrap();
+    /// builder.page_template(r#"<?xml version="1.0" encoding="UTF-8"?>
+    /// <!DOCTYPE html>
+    /// <html xmlns="http://www.w3.org/1999/xhtml" xml:lang="{{ lang_attr }}">
+    /// <head>
+    ///   <title>{{ title }}</title>
+    ///   <link rel="stylesheet" type="text/css" href="{{ stylesheet_attr }}" />
+    /// </head>
+    /// <body class="chapter">
+    /// {{ body }}
+    /// </body>
+    /// </html>"#).unwrap();
+    /// ```
+    pub fn page_template<S: Into<String>>(&mut self, template: S) -> Result<&mut Self> {
+        let template = templates::ENGINE
+            .compile(template.into())
+            .map_err(|e| crate::Error::TemplateError {
+                msg: "error compiling page template".to_string(),
+                cause: e.into(),
+            })?;
+        self.page_template = Some(template);
+        Ok(self)
+    }
+
     /// Adds an inline toc in the document.
     ///
     /// If this method is called it adds a page that contains the table of contents
@@ -427,17 +917,23 @@ impl<Z: Zip> EpubBuilder<Z> {
     ///
     /// The position where this table of contents will be inserted depends on when
     /// you call this method: if you call it before adding any content, it will be
-    /// at the beginning, if you call it after, it will be at the end.
+    /// at the beginning, if you call it after, it will be at the end. It can then
+    /// be moved like any other file, using the `toc.xhtml` href (see `move_before`).
+    /// If another file was already added at this href, generating the EPUB fails.
     pub fn inline_toc(&mut self) -> &mut Self {
+        if self.inline_toc {
+            return self;
+        }
         self.inline_toc = true;
-        self.toc.add(TocElement::new(
-            "toc.xhtml",
-            self.metadata.toc_name.as_str(),
-        ));
         let mut file = Content::new("toc.xhtml", "application/xhtml+xml");
         file.reftype = Some(ReferenceType::Toc);
-        file.title = self.metadata.toc_name.clone();
+        file.title = self.label(Label::Toc).to_string();
         file.itemref = true;
+        file.toc = Some(TocElement::new(
+            "toc.xhtml",
+            self.label(Label::Toc),
+        ));
+        file.id = self.unique_id(&to_id(&file.file));
         self.files.push(file);
         self
     }
@@ -455,24 +951,102 @@ impl<Z: Zip> EpubBuilder<Z> {
     ///
     /// * `path`: the path where this file will be written in the EPUB OEBPS structure,
     ///   e.g. `data/image_0.png`
-    /// * `content`: the resource to include
+    /// * `content`: the resource to include, either a reader (which is read in memory
+    ///   immediately) or a [`ContentSource`](enum.ContentSource.html) (which is only read
+    ///   when the EPUB is generated)
     /// * `mime_type`: the mime type of this file, e.g. "image/png".
     pub fn add_resource<R, P, S>(&mut self, path: P, content: R, mime_type: S) -> Result<&mut Self>
     where
-        R: Read,
+        R: IntoContentSource,
+        P: AsRef<Path>,
+        S: Into<String>,
+    {
+        self.add_resource_with_options(path, content, mime_type, ResourceOptions::default())
+    }
+
+    /// Add a resource to the EPUB file, with some options
+    ///
+    /// This works like `add_resource`, except that `options` allows to customize how it
+    /// is added.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use epub_builder::{EpubBuilder, ZipLibrary};
+    /// use epub_builder::{Compression, ResourceOptions};
+    /// # let mut builder = EpubBuilder::new(ZipLibrary::new().unwrap()).unwrap();
+    /// let image = "Should be a JPEG image";
+    /// // Store the image, since deflating it would be useless
+    /// builder.add_resource_with_options("image.jpg", image.as_bytes(), "image/jpeg",
+    ///                                   ResourceOptions::new().compression(Compression::Stored))
+    ///        .unwrap();
+    /// ```
+    pub fn add_resource_with_options<R, P, S>(
+        &mut self,
+        path: P,
+        content: R,
+        mime_type: S,
+        options: ResourceOptions,
+    ) -> Result<&mut Self>
+    where
+        R: IntoContentSource,
         P: AsRef<Path>,
         S: Into<String>,
     {
-        self.zip
-            .write_file(Path::new("OEBPS").join(path.as_ref()), content)?;
         log::debug!("Add resource: {:?}", path.as_ref().display());
-        self.files.push(Content::new(
-            format!("{}", path.as_ref().display()),
-            mime_type,
-        ));
+        let href = common::normalize_path(path.as_ref())?;
+        self.check_href(&href)?;
+        let mut file = Content::new(href, mime_type);
+        file.source = Some(read_source(path.as_ref(), content)?);
+        file.compression = options.compression.map(Compression::validate).transpose()?;
+        file.id = options.id.unwrap_or_default();
+        if self.deduplicate {
+            self.deduplicate_resource(file)?;
+        } else {
+            self.insert_file(self.files.len(), file)?;
+        }
         Ok(self)
     }
 
+    /// Adds a resource, unless an identical one was already added
+    fn deduplicate_resource(&mut self, mut file: Content) -> Result<()> {
+        let bytes = match file.source.take() {
+            Some(ContentSource::Bytes(bytes)) => bytes,
+            Some(source) => source.read_to_vec().map_err(|e| crate::Error::IoError {
+                msg: format!("could not read file '{}'", file.file),
+                cause: e,
+            })?,
+            None => vec![],
+        };
+        let mut hasher = DefaultHasher::new();
+        bytes.hash(&mut hasher);
+        let hrefs = self.resource_hashes.entry(hasher.finish()).or_default();
+        let canonical = hrefs.iter().find(|href| {
+            self.files.iter().any(|other| {
+                &other.file == *href
+                    && other.mime == file.mime
+                    && other.compression == file.compression
+                    && matches!(other.source,
+                                Some(ContentSource::Bytes(ref other)) if *other == bytes)
+            })
+        });
+        match canonical {
+            // A resource with an explicit id needs its own item in the manifest
+            Some(canonical) if *canonical != file.file && file.id.is_empty() => {
+                log::debug!("Resource {} is a duplicate of {}", file.file, canonical);
+                self.deduplication.duplicates += 1;
+                self.deduplication.bytes_saved += bytes.len() as u64;
+                self.aliases.insert(file.file, canonical.clone());
+            }
+            _ => {
+                hrefs.push(file.file.clone());
+                file.source = Some(ContentSource::Bytes(bytes));
+                self.insert_file(self.files.len(), file)?;
+            }
+        }
+        Ok(())
+    }
+
     /// Add a cover image to the EPUB.
     ///
     /// This works similarly to adding the image as a resource with the `add_resource`
@@ -485,92 +1059,723 @@ impl<Z: Zip> EpubBuilder<Z> {
         mime_type: S,
     ) -> Result<&mut Self>
     where
-        R: Read,
+        R: IntoContentSource,
         P: AsRef<Path>,
         S: Into<String>,
     {
-        self.zip
-            .write_file(Path::new("OEBPS").join(path.as_ref()), content)?;
-        let mut file = Content::new(format!("{}", path.as_ref().display()), mime_type);
+        let href = common::normalize_path(path.as_ref())?;
+        self.check_href(&href)?;
+        let mut file = Content::new(href, mime_type);
+        file.source = Some(read_source(path.as_ref(), content)?);
         file.cover = true;
-        self.files.push(file);
+        self.insert_file(self.files.len(), file)?;
         Ok(self)
     }
 
-    /// Add a XHTML content file that will be added to the EPUB.
+    /// Generates a cover page displaying the cover image.
     ///
-    /// # Examples
+    /// Some reading systems don't display the image added with `add_cover_image` as a
+    /// cover, so this adds a `cover.xhtml` page at the beginning of the book, showing the
+    /// image scaled to fit the screen. It is added when the EPUB is generated, and only if
+    /// a cover image was added.
     ///
-    /// ```
-    /// # use epub_builder::{EpubBuilder, ZipLibrary, EpubContent};
-    /// let content = "Some content";
-    /// let mut builder = EpubBuilder::new(ZipLibrary::new().unwrap()).unwrap();
-    /// // Add a chapter that won't be added to the Table of Contents
-    /// builder.add_content(EpubContent::new("intro.xhtml", content.as_bytes())).unwrap();
-    /// ```
+    /// If `linear` is `false`, the page is not part of the reading order, which means
+    /// reading systems may only show it as the cover of the book.
+    ///
+    /// # Example
     ///
     /// ```
-    /// # use epub_builder::{EpubBuilder, ZipLibrary, EpubContent, TocElement};
+    /// # use epub_builder::{EpubBuilder, ZipLibrary};
     /// # let mut builder = EpubBuilder::new(ZipLibrary::new().unwrap()).unwrap();
-    /// # let content = "Some content";
-    /// // Sets the title of a chapter so it is added to the Table of contents
-    /// // Also add information about its structure
-    /// builder.add_content(EpubContent::new("chapter_1.xhtml", content.as_bytes())
-    ///                      .title("Chapter 1")
-    ///                      .child(TocElement::new("chapter_1.xhtml#1", "1.1"))).unwrap();
+    /// # let image = "Should be a PNG image";
+    /// builder.add_cover_image("cover.png", image.as_bytes(), "image/png").unwrap();
+    /// builder.cover_page(true);
     /// ```
+    pub fn cover_page(&mut self, linear: bool) -> &mut Self {
+        self.cover_page = Some(linear);
+        self
+    }
+
+    /// Generates a page from the metadata of the book.
+    ///
+    /// The page is added when the EPUB is generated, at `position` in the spine, and is
+    /// referenced in the guide and landmarks. Calling this again for the same page only
+    /// changes its position.
+    ///
+    /// # Example
     ///
     /// ```
-    /// # use epub_builder::{EpubBuilder, ZipLibrary, EpubContent};
+    /// # use epub_builder::{EpubBuilder, ZipLibrary};
+    /// use epub_builder::{GeneratedPage, SpinePosition};
     /// # let mut builder = EpubBuilder::new(ZipLibrary::new().unwrap()).unwrap();
-    /// # let content = "Some content";
-    /// // Add a section, by setting the level to 2 (instead of the default value 1)
-    /// builder.add_content(EpubContent::new("section.xhtml", content.as_bytes())
-    ///                      .title("Section 1")
-    ///                      .level(2)).unwrap();
+    /// builder
+    ///     .generate_page(GeneratedPage::TitlePage, SpinePosition::Start)
+    ///     .generate_page(GeneratedPage::Copyright, SpinePosition::Start)
+    ///     .generate_page(GeneratedPage::Colophon, SpinePosition::End);
     /// ```
+    pub fn generate_page(&mut self, page: GeneratedPage, position: SpinePosition) -> &mut Self {
+        match self.generated_pages.iter_mut().find(|(other, _)| *other == page) {
+            Some((_, existing)) => *existing = position,
+            None => self.generated_pages.push((page, position)),
+        }
+        self
+    }
+
+    /// Sets the template used to render a generated page.
     ///
-    /// Note that these files will automatically be inserted into an `OEBPS` directory,
-    /// so you don't need (and shouldn't) prefix your path with `OEBPS/`.
+    /// The template renders the body of the page, which is then wrapped like other
+    /// fragments (see `page_template`). It uses the [upon](https://docs.rs/upon) syntax,
+    /// with the following values, which are already escaped:
     ///
-    /// # See also
+    /// * `title`, `publisher`, `license`, `generator`;
+    /// * `authors`: the list of authors, and `author_names`, all of them in one string;
+    /// * `date_published` and `date_modified`, as `YYYY-MM-DD`, and `year`, the year of
+    ///   publication (or of modification if there is no publication date);
+    /// * `identifier`: the unique identifier of the book;
+    /// * `labels.published_by`, `labels.by` and `labels.generated_with`: the corresponding
+    ///   [`Label`](enum.Label.html)s.
     ///
-    /// * [`EpubContent`](struct.EpubContent.html)
-    /// * the `add_resource` method, to add other resources in the EPUB file.
-    pub fn add_content<R: Read>(&mut self, content: EpubContent<R>) -> Result<&mut Self> {
-        self.zip.write_file(
-            Path::new("OEBPS").join(content.toc.url.as_str()),
-            content.content,
-        )?;
-        let mut file = Content::new(content.toc.url.as_str(), "application/xhtml+xml");
-        file.itemref = true;
-        file.reftype = content.reftype;
-        if file.reftype.is_some() {
-            file.title = content.toc.title.clone();
-        }
-        self.files.push(file);
-        if !content.toc.title.is_empty() {
-            self.toc.add(content.toc);
-        }
+    /// # Example
+    ///
+    /// ```
+    /// # use epub_builder::{EpubBuilder, ZipLibrary};
+    /// use epub_builder::{GeneratedPage, SpinePosition};
+    /// # let mut builder = EpubBuilder::new(ZipLibrary::new().unwrap()).unwrap();
+    /// builder
+    ///     .generated_page_template(GeneratedPage::TitlePage,
+    ///                              "<h1>{{ title }}</h1><p>{{ author_names }}</p>")
+    ///     .unwrap()
+    ///     .generate_page(GeneratedPage::TitlePage, SpinePosition::Start);
+    /// ```
+    pub fn generated_page_template<S: Into<String>>(
+        &mut self,
+        page: GeneratedPage,
+        template: S,
+    ) -> Result<&mut Self> {
+        let template = templates::ENGINE
+            .compile(template.into())
+            .map_err(|e| crate::Error::TemplateError {
+                msg: format!("error compiling {} template", page.file()),
+                cause: e.into(),
+            })?;
+        self.generated_page_templates.insert(page, template);
         Ok(self)
     }
 
-    /// Generate the EPUB file and write it to the writer
+    /// Sets how notes are displayed (default: `Footnotes`)
+    ///
+    /// This must be called before adding notes, since it changes the links to them.
+    pub fn note_style(&mut self, style: NoteStyle) -> &mut Self {
+        self.note_style = style;
+        self
+    }
+
+    /// Adds a note to a chapter, and returns the link to insert in the chapter where
+    /// the note is referenced.
+    ///
+    /// Notes are numbered in the order they are added. The builder writes them when the
+    /// EPUB is generated, depending on `note_style`: either at the end of the chapter's
+    /// body, or in a `notes.xhtml` document at the end of the book. Both the link and the
+    /// note have a link to each other, using `noteref-N` and `note-N` ids.
+    ///
+    /// `content` is XHTML, e.g. `<p>Some note</p>`, and is not escaped. As the link
+    /// depends on the version of the EPUB, `epub_version` must be called before this.
     ///
     /// # Example
     ///
     /// ```
-    /// # use epub_builder::{EpubBuilder, ZipLibrary};
-    /// let mut builder = EpubBuilder::new(ZipLibrary::new().unwrap()).unwrap();
-    /// // Write the EPUB file into a Vec<u8>
+    /// # use epub_builder::{EpubBuilder, ZipLibrary, EpubContent};
+    /// use epub_builder::NoteStyle;
+    /// # let mut builder = EpubBuilder::new(ZipLibrary::new().unwrap()).unwrap();
+    /// builder.note_style(NoteStyle::Endnotes);
+    /// let noteref = builder.add_note("chapter_1.xhtml", "<p>A note.</p>").unwrap();
+    /// let body = format!("<p>Some text{noteref}.</p>");
+    /// builder.add_content(EpubContent::from_fragment("chapter_1.xhtml", body.as_bytes()))
+    ///        .unwrap();
+    /// ```
+    pub fn add_note<S: Into<String>>(&mut self, chapter: &str, content: S) -> Result<String> {
+        let note = Note {
+            number: self.notes.len() + 1,
+            chapter: common::normalize_path(Path::new(chapter))?,
+            content: content.into(),
+        };
+        let noteref = notes::render_noteref(self.version, self.note_style, &note);
+        self.notes.push(note);
+        Ok(noteref)
+    }
+
+    /// Adds an entry to the glossary, and returns the href of the entry, to link to it
+    ///
+    /// If there are entries, the builder generates a `glossary.xhtml` document at the end of
+    /// the book when the EPUB is generated, listing the terms alphabetically with
+    /// `glossterm` and `glossdef` semantics. In EPUB 3, the document is also declared as a
+    /// glossary in the package document.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use epub_builder::{EpubBuilder, ZipLibrary};
+    /// use epub_builder::GlossaryEntry;
+    /// # let mut builder = EpubBuilder::new(ZipLibrary::new().unwrap()).unwrap();
+    /// let href = builder.add_glossary_entry(GlossaryEntry::new("EPUB", "<p>A format.</p>"));
+    /// assert_eq!(href, "glossary.xhtml#glossary-1");
+    /// ```
+    pub fn add_glossary_entry(&mut self, entry: GlossaryEntry) -> String {
+        self.glossary.push(entry);
+        format!("{}#glossary-{}", glossary::GLOSSARY_FILE, self.glossary.len())
+    }
+
+    /// Adds an entry to the dictionary, and returns the href of the entry, to link to it
+    ///
+    /// This is similar to `add_glossary_entry`, but the builder generates a
+    /// `dictionary.xhtml` document following EPUB Dictionaries: in EPUB 3, it also
+    /// generates a search key map with the forms of each term, so that reading systems
+    /// can look them up, and declares both in a `dictionary` collection.
+    pub fn add_dictionary_entry(&mut self, entry: GlossaryEntry) -> String {
+        self.dictionary.push(entry);
+        format!("{}#dictionary-{}", glossary::DICTIONARY_FILE, self.dictionary.len())
+    }
+
+    /// Sets the language the terms of the dictionary are defined in (default: the language
+    /// of the book)
+    ///
+    /// The language of the terms themselves is the language of the book. Both are declared
+    /// in the `dictionary` collection, in EPUB 3.
+    pub fn dictionary_target_language<S: Into<String>>(&mut self, lang: S) -> &mut Self {
+        self.dictionary_target_language = Some(lang.into());
+        self
+    }
+
+    /// Adds a citation to the bibliography, and returns the href of its entry, to link to it
+    ///
+    /// If there are citations, the builder generates a `bibliography.xhtml` document at the
+    /// end of the book when the EPUB is generated, with an entry for each citation, whose id
+    /// is the key of the citation. Entries are formatted with `bibliography_formatter`.
+    ///
+    /// Returns an error if the key is not a valid XML id, or is already used.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use epub_builder::{EpubBuilder, ZipLibrary};
+    /// use epub_builder::Citation;
+    /// # let mut builder = EpubBuilder::new(ZipLibrary::new().unwrap()).unwrap();
+    /// let href = builder
+    ///     .add_citation(Citation::new("knuth1984", "Literate Programming").year(1984))
+    ///     .unwrap();
+    /// assert_eq!(href, "bibliography.xhtml#knuth1984");
+    /// ```
+    pub fn add_citation(&mut self, citation: Citation) -> Result<String> {
+        if !is_valid_id(&citation.key) {
+            return Err(crate::Error::InvalidId(citation.key));
+        }
+        if self.citations.iter().any(|other| other.key == citation.key) {
+            return Err(crate::Error::DuplicateId(citation.key));
+        }
+        let href = format!("{}#{}", bibliography::BIBLIOGRAPHY_FILE, citation.key);
+        self.citations.push(citation);
+        Ok(href)
+    }
+
+    /// Sets how the entries of the bibliography are formatted and sorted
+    ///
+    /// By default, [`DefaultCitationFormatter`](struct.DefaultCitationFormatter.html) is used.
+    pub fn bibliography_formatter<F: CitationFormatter + 'static>(
+        &mut self,
+        formatter: F,
+    ) -> &mut Self {
+        self.citation_formatter = Box::new(formatter);
+        self
+    }
+
+    /// Adds an entry to the index
+    ///
+    /// If there are entries, the builder generates an `index.xhtml` document at the end of
+    /// the book when the EPUB is generated, following the EPUB Indexes specification.
+    /// Entries are sorted alphabetically, and entries with the same term are merged.
+    ///
+    /// Locators must point to content of the EPUB, usually with a fragment, e.g.
+    /// `chapter_1.xhtml#cat`; the corresponding `id` must be set in the content.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use epub_builder::{EpubBuilder, ZipLibrary};
+    /// use epub_builder::IndexEntry;
+    /// # let mut builder = EpubBuilder::new(ZipLibrary::new().unwrap()).unwrap();
+    /// builder.add_index_entry(IndexEntry::new("Cat").locator("chapter_1.xhtml#cat"))
+    ///        .unwrap()
+    ///        .add_index_entry(IndexEntry::new("Feline").see("Cat"))
+    ///        .unwrap();
+    /// ```
+    pub fn add_index_entry(&mut self, mut entry: IndexEntry) -> Result<&mut Self> {
+        entry.normalize()?;
+        index::add_entry(&mut self.index, entry);
+        Ok(self)
+    }
+
+    /// Add a XHTML content file that will be added to the EPUB.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub_builder::{EpubBuilder, ZipLibrary, EpubContent};
+    /// let content = "Some content";
+    /// let mut builder = EpubBuilder::new(ZipLibrary::new().unwrap()).unwrap();
+    /// // Add a chapter that won't be added to the Table of Contents
+    /// builder.add_content(EpubContent::new("intro.xhtml", content.as_bytes())).unwrap();
+    /// ```
+    ///
+    /// ```
+    /// # use epub_builder::{EpubBuilder, ZipLibrary, EpubContent, TocElement};
+    /// # let mut builder = EpubBuilder::new(ZipLibrary::new().unwrap()).unwrap();
+    /// # let content = "Some content";
+    /// // Sets the title of a chapter so it is added to the Table of contents
+    /// // Also add information about its structure
+    /// builder.add_content(EpubContent::new("chapter_1.xhtml", content.as_bytes())
+    ///                      .title("Chapter 1")
+    ///                      .child(TocElement::new("chapter_1.xhtml#1", "1.1"))).unwrap();
+    /// ```
+    ///
+    /// ```
+    /// # use epub_builder::{EpubBuilder, ZipLibrary, EpubContent};
+    /// # let mut builder = EpubBuilder::new(ZipLibrary::new().unwrap()).unwrap();
+    /// # let content = "Some content";
+    /// // Add a section, by setting the level to 2 (instead of the default value 1)
+    /// builder.add_content(EpubContent::new("section.xhtml", content.as_bytes())
+    ///                      .title("Section 1")
+    ///                      .level(2)).unwrap();
+    /// ```
+    ///
+    /// Note that these files will automatically be inserted into an `OEBPS` directory,
+    /// so you don't need (and shouldn't) prefix your path with `OEBPS/`.
+    ///
+    /// As with `add_resource`, the content can be a reader, which is read in memory
+    /// immediately, or a [`ContentSource`](enum.ContentSource.html), which is only read
+    /// when the EPUB is generated.
+    ///
+    /// # See also
+    ///
+    /// * [`EpubContent`](struct.EpubContent.html)
+    /// * the `add_resource` method, to add other resources in the EPUB file.
+    pub fn add_content<R: IntoContentSource>(
+        &mut self,
+        content: EpubContent<R>,
+    ) -> Result<&mut Self> {
+        let file = content_file(content)?;
+        self.check_href(&file.file)?;
+        self.insert_file(self.files.len(), file)?;
+        Ok(self)
+    }
+
+    /// Add a XHTML content file just before the file at `href`.
+    ///
+    /// This works like `add_content`, except for the position of the new file in the
+    /// spine and in the table of contents.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use epub_builder::{EpubBuilder, ZipLibrary, EpubContent};
+    /// # let mut builder = EpubBuilder::new(ZipLibrary::new().unwrap()).unwrap();
+    /// builder.add_content(EpubContent::new("chapter_1.xhtml", "".as_bytes())).unwrap();
+    /// builder.insert_content_before("chapter_1.xhtml",
+    ///                               EpubContent::new("intro.xhtml", "".as_bytes())).unwrap();
+    /// assert_eq!(builder.spine().collect::<Vec<_>>(), ["intro.xhtml", "chapter_1.xhtml"]);
+    /// ```
+    pub fn insert_content_before<R: IntoContentSource>(
+        &mut self,
+        href: &str,
+        content: EpubContent<R>,
+    ) -> Result<&mut Self> {
+        let index = self.position(href)?;
+        let file = content_file(content)?;
+        self.check_href(&file.file)?;
+        self.insert_file(index, file)?;
+        Ok(self)
+    }
+
+    /// Add a XHTML content file just after the file at `href`.
+    ///
+    /// This works like `insert_content_before`, except the new file is inserted after `href`.
+    pub fn insert_content_after<R: IntoContentSource>(
+        &mut self,
+        href: &str,
+        content: EpubContent<R>,
+    ) -> Result<&mut Self> {
+        let index = self.position(href)?;
+        let file = content_file(content)?;
+        self.check_href(&file.file)?;
+        self.insert_file(index + 1, file)?;
+        Ok(self)
+    }
+
+    /// Returns the hrefs of the files of the spine (the ones added with `add_content` or
+    /// `inline_toc`), in reading order
+    pub fn spine(&self) -> impl Iterator<Item = &str> {
+        self.files
+            .iter()
+            .filter(|file| file.itemref)
+            .map(|file| file.file.as_str())
+    }
+
+    /// Returns the hrefs of the resources (files that are not part of the spine), in the
+    /// order in which they appear in the manifest
+    pub fn resources(&self) -> impl Iterator<Item = &str> {
+        self.files
+            .iter()
+            .filter(|file| !file.itemref)
+            .map(|file| file.file.as_str())
+    }
+
+    /// Moves the file at `href` just before the file at `target`.
+    ///
+    /// For content files, this changes the reading order, and the position of their entries
+    /// in the table of contents; for resources, only the order of the manifest.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use epub_builder::{EpubBuilder, ZipLibrary, EpubContent};
+    /// # let mut builder = EpubBuilder::new(ZipLibrary::new().unwrap()).unwrap();
+    /// builder.add_content(EpubContent::new("chapter_1.xhtml", "".as_bytes())).unwrap();
+    /// // Put the inline table of contents at the beginning
+    /// builder.inline_toc();
+    /// builder.move_before("toc.xhtml", "chapter_1.xhtml").unwrap();
+    /// assert_eq!(builder.spine().collect::<Vec<_>>(), ["toc.xhtml", "chapter_1.xhtml"]);
+    /// ```
+    pub fn move_before(&mut self, href: &str, target: &str) -> Result<&mut Self> {
+        self.move_file(href, target, 0)
+    }
+
+    /// Moves the file at `href` just after the file at `target`.
+    ///
+    /// See `move_before`.
+    pub fn move_after(&mut self, href: &str, target: &str) -> Result<&mut Self> {
+        self.move_file(href, target, 1)
+    }
+
+    /// Removes the file at `href`, whether it is a content file or a resource.
+    ///
+    /// Its entry in the table of contents, if any, is removed too.
+    ///
+    /// If resources are deduplicated (see `deduplicate_resources`), removing a duplicate
+    /// keeps the resource it was identical to, and removing a resource that has duplicates
+    /// stores it under the href of one of them instead, which `canonical_href` returns.
+    pub fn remove(&mut self, href: &str) -> Result<&mut Self> {
+        let normalized = common::normalize_path(Path::new(href))?;
+        if let Some(canonical) = self.aliases.remove(&normalized) {
+            self.forget_duplicate(&canonical);
+            return Ok(self);
+        }
+        let index = self.position(href)?;
+        let href = self.files[index].file.clone();
+        let promoted = self
+            .aliases
+            .iter()
+            .filter(|(_, canonical)| **canonical == href)
+            .map(|(alias, _)| alias.clone())
+            .min();
+        if let Some(promoted) = promoted {
+            self.aliases.remove(&promoted);
+            self.forget_duplicate(&href);
+            for canonical in self.aliases.values_mut() {
+                if *canonical == href {
+                    *canonical = promoted.clone();
+                }
+            }
+            for hrefs in self.resource_hashes.values_mut() {
+                for other in hrefs.iter_mut().filter(|other| **other == href) {
+                    *other = promoted.clone();
+                }
+            }
+            self.files[index].file = promoted;
+            return Ok(self);
+        }
+        let removed = self.files.remove(index);
+        for hrefs in self.resource_hashes.values_mut() {
+            hrefs.retain(|other| *other != href);
+        }
+        if removed.stylesheet {
+            self.stylesheet = false;
+        }
+        if href == "toc.xhtml" {
+            self.inline_toc = false;
+        }
+        Ok(self)
+    }
+
+    /// Updates the deduplication report when a duplicate of `canonical` is removed
+    fn forget_duplicate(&mut self, canonical: &str) {
+        let size = self
+            .files
+            .iter()
+            .find(|file| file.file == canonical)
+            .and_then(|file| match file.source {
+                Some(ContentSource::Bytes(ref bytes)) => Some(bytes.len() as u64),
+                _ => None,
+            })
+            .unwrap_or_default();
+        self.deduplication.duplicates -= 1;
+        self.deduplication.bytes_saved -= size;
+    }
+
+    /// Moves a file at `offset` from the position of `target`
+    fn move_file(&mut self, href: &str, target: &str, offset: usize) -> Result<&mut Self> {
+        let index = self.position(href)?;
+        self.position(target)?;
+        if href != target {
+            let file = self.files.remove(index);
+            let index = self.position(target)? + offset;
+            self.files.insert(index, file);
+        }
+        Ok(self)
+    }
+
+    /// Returns the position of the file at `href`
+    fn position(&self, href: &str) -> Result<usize> {
+        let normalized = common::normalize_path(Path::new(href))?;
+        self.files
+            .iter()
+            .position(|file| file.file == normalized)
+            .ok_or_else(|| crate::Error::HrefNotFound(href.to_string()))
+    }
+
+    /// Returns the id of the file at `href` in the manifest, or `None` if no file was
+    /// added at this path.
+    ///
+    /// Unless an explicit id was given when adding the file, it is derived from its path,
+    /// with a suffix if needed to make it unique.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use epub_builder::{EpubBuilder, ZipLibrary, EpubContent};
+    /// # let mut builder = EpubBuilder::new(ZipLibrary::new().unwrap()).unwrap();
+    /// builder.add_content(EpubContent::new("chapter 1.xhtml", "".as_bytes())).unwrap();
+    /// builder.add_content(EpubContent::new("chapter_1.xhtml", "".as_bytes())).unwrap();
+    /// builder.add_content(EpubContent::new("intro.xhtml", "".as_bytes()).id("intro")).unwrap();
+    /// assert_eq!(builder.manifest_id("chapter 1.xhtml"), Some("id_chapter_1.xhtml"));
+    /// assert_eq!(builder.manifest_id("chapter_1.xhtml"), Some("id_chapter_1.xhtml_2"));
+    /// assert_eq!(builder.manifest_id("intro.xhtml"), Some("intro"));
+    /// ```
+    pub fn manifest_id(&self, href: &str) -> Option<&str> {
+        let href = self.canonical_href(href)?;
+        self.files
+            .iter()
+            .find(|file| file.file == href)
+            .map(|file| file.id.as_str())
+    }
+
+    /// Inserts a file at `index`, after setting its id in the manifest
+    ///
+    /// If `file.id` is already set, it is an id given by the caller, which is only checked.
+    fn insert_file(&mut self, index: usize, mut file: Content) -> Result<()> {
+        if file.id.is_empty() {
+            let preferred = if file.cover {
+                String::from("cover-image")
+            } else {
+                to_id(&file.file)
+            };
+            file.id = self.unique_id(&preferred);
+        } else if !is_valid_id(&file.id) {
+            return Err(crate::Error::InvalidId(file.id));
+        } else if self.is_id_taken(&file.id) {
+            return Err(crate::Error::DuplicateId(file.id));
+        }
+        self.files.insert(index, file);
+        Ok(())
+    }
+
+    /// Returns `preferred`, or `preferred` with a suffix if it is already used
+    fn unique_id(&self, preferred: &str) -> String {
+        let mut id = preferred.to_string();
+        let mut suffix = 1;
+        while self.is_id_taken(&id) {
+            suffix += 1;
+            id = format!("{preferred}_{suffix}");
+        }
+        id
+    }
+
+    /// Tells whether an id is already used in the manifest, or reserved for the ids the
+    /// package document gives to its metadata (`epub-id-1`, `epub-title-1`...)
+    fn is_id_taken(&self, id: &str) -> bool {
+        ["ncx", "nav"].contains(&id)
+            || id.starts_with("epub-")
+            || self.files.iter().any(|file| file.id == id)
+    }
+
+    /// Makes sure a new file can be added at `href`, which must already be normalized
+    fn check_href(&self, href: &str) -> Result<()> {
+        let existing = self
+            .files
+            .iter()
+            .map(|file| file.file.as_str())
+            .chain(self.aliases.keys().map(String::as_str))
+            .chain(["content.opf", "toc.ncx", "nav.xhtml"]);
+        for existing in existing {
+            if existing == href {
+                return Err(crate::Error::DuplicatePath(href.to_string()));
+            }
+            if existing.to_lowercase() == href.to_lowercase() {
+                return Err(crate::Error::PathCollision {
+                    path: href.to_string(),
+                    existing: existing.to_string(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Generate the EPUB file and write it to the writer
+    ///
+    /// With [`ZipStream`](struct.ZipStream.html), the EPUB has already been written to the
+    /// writer given to `ZipStream::new`, so `to` is not used.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use epub_builder::{EpubBuilder, ZipLibrary};
+    /// let mut builder = EpubBuilder::new(ZipLibrary::new().unwrap()).unwrap();
+    /// // Write the EPUB file into a Vec<u8>
     /// let mut epub: Vec<u8> = vec!();
     /// builder.generate(&mut epub).unwrap();
     /// ```
-    pub fn generate<W: io::Write>(mut self, to: W) -> Result<()> {
+    pub fn generate<W: io::Write>(self, to: W) -> Result<()> {
+        self.into_zip()?.generate(to)?;
+        Ok(())
+    }
+
+    /// Write all the files of the EPUB to the zip implementation and return it, without
+    /// generating the zip file.
+    ///
+    /// This is mostly useful with [`ZipMemory`](struct.ZipMemory.html), to look at the
+    /// generated files in tests.
+    pub fn into_zip(mut self) -> Result<Z> {
+        if self.validate_subject_codes {
+            for subject in &self.metadata.structured_subjects {
+                subject.validate()?;
+            }
+        }
+        // `inline_toc` can't return an error, so a file that was added at its path before
+        // it was called is only reported now
+        if let Some(index) = self
+            .files
+            .iter()
+            .position(|file| file.source.is_none() && file.file == "toc.xhtml")
+        {
+            let toc = self.files.remove(index);
+            self.check_href(&toc.file)?;
+            self.files.insert(index, toc);
+        }
         // If no styleesheet was provided, generate a dummy one
         if !self.stylesheet {
             self.stylesheet(b"".as_ref())?;
         }
+        if self.reproducible {
+            self.zip.set_timestamp(reproducible_timestamp());
+        }
+        // Resolve the values used in several files
+        if self.metadata.uuid.is_none() {
+            self.metadata.uuid = Some(if self.reproducible {
+                self.stable_uuid()
+            } else {
+                uuid::Uuid::new_v4()
+            });
+        }
+        if self.metadata.date_modified.is_none() {
+            self.metadata.date_modified = Some(if self.reproducible {
+                reproducible_timestamp()
+            } else {
+                chrono::Utc::now()
+            });
+        }
+        if self.note_style == NoteStyle::Endnotes && !self.notes.is_empty() {
+            self.add_endnotes()?;
+        }
+        if !self.glossary.is_empty() {
+            self.add_glossary()?;
+        }
+        if !self.dictionary.is_empty() {
+            self.add_dictionary()?;
+        }
+        if !self.citations.is_empty() {
+            self.add_bibliography()?;
+        }
+        if !self.index.is_empty() {
+            self.add_index()?;
+        }
+        self.add_generated_pages()?;
+        if let Some(linear) = self.cover_page {
+            self.add_cover_page(linear)?;
+        }
+        if self.inline_toc {
+            // The language, and so the name of the table of contents, may have changed
+            // since `inline_toc` was called
+            let toc_name = self.label(Label::Toc).to_string();
+            if let Some(file) = self.files.iter_mut().find(|file| file.file == "toc.xhtml") {
+                if let Some(ref mut element) = file.toc {
+                    element.title = toc_name.clone();
+                }
+                file.title = toc_name;
+            }
+        }
+        // Build the table of contents, following the final order of the files
+        self.toc = Toc::new();
+        for file in &mut self.files {
+            if let Some(element) = file.toc.take() {
+                self.toc.add(element);
+            }
+        }
+        self.zip
+            .write_file("META-INF/container.xml", templates::CONTAINER)?;
+        self.zip.write_file(
+            "META-INF/com.apple.ibooks.display-options.xml",
+            templates::IBOOKS,
+        )?;
+        // Write the files that were added, reading their content only now
+        for file in &self.files {
+            if let Some(ref source) = file.source {
+                let path = Path::new("OEBPS").join(&file.file);
+                let content = source.open().map_err(|e| crate::Error::IoError {
+                    msg: format!("could not read file '{}'", file.file),
+                    cause: e,
+                })?;
+                let footnotes = self.notes.iter().filter(|note| {
+                    self.note_style == NoteStyle::Footnotes && note.chapter == file.file
+                });
+                let footnotes = notes::render_footnotes(self.version, footnotes);
+                let rewrite = file.fragment || file.xml_prolog || !footnotes.is_empty();
+                let content: Box<dyn io::Read> = if rewrite {
+                    let mut body = read_string(&file.file, content)?;
+                    let page = if file.fragment {
+                        if !footnotes.is_empty() {
+                            body.push('\n');
+                            body.push_str(&footnotes);
+                        }
+                        self.render_page(file, &body)?
+                    } else {
+                        if !footnotes.is_empty() {
+                            notes::insert_in_body(&mut body, &footnotes);
+                        }
+                        if file.xml_prolog {
+                            body.insert_str(0, &xml_prolog(self.version));
+                        }
+                        body.into_bytes()
+                    };
+                    Box::new(io::Cursor::new(page))
+                } else {
+                    content
+                };
+                match file.compression {
+                    Some(compression) => {
+                        self.zip.write_file_compressed(path, content, compression)?
+                    }
+                    None => self.zip.write_file(path, content)?,
+                }
+            }
+        }
         // Render content.opf
         let bytes = self.render_opf()?;
         self.zip.write_file("OEBPS/content.opf", &*bytes)?;
@@ -586,10 +1791,282 @@ impl<Z: Zip> EpubBuilder<Z> {
             self.zip.write_file("OEBPS/toc.xhtml", &*bytes)?;
         }
 
-        self.zip.generate(to)?;
+        Ok(self.zip)
+    }
+
+    /// Adds the pages requested with `generate_page`
+    fn add_generated_pages(&mut self) -> Result<()> {
+        let mut start = self
+            .files
+            .iter()
+            .position(|file| file.itemref)
+            .unwrap_or(self.files.len());
+        for (page, position) in std::mem::take(&mut self.generated_pages) {
+            let body = self.render_generated_page(page)?;
+            self.check_href(page.file())?;
+            let mut file = Content::new(page.file(), "application/xhtml+xml");
+            file.source = Some(ContentSource::bytes(body));
+            file.fragment = true;
+            file.page_title = self.label(page.label()).to_string();
+            file.itemref = true;
+            file.reftype = Some(page.reftype());
+            file.title = self.label(page.label()).to_string();
+            let index = match position {
+                SpinePosition::Start => start,
+                SpinePosition::End => self.files.len(),
+                SpinePosition::Before(ref href) => self.position(href)?,
+                SpinePosition::After(ref href) => self.position(href)? + 1,
+            };
+            if index <= start {
+                start += 1;
+            }
+            self.insert_file(index, file)?;
+        }
+        Ok(())
+    }
+
+    /// Renders the body of a generated page
+    fn render_generated_page(&self, page: GeneratedPage) -> Result<String> {
+        let authors: Vec<_> = self
+            .metadata
+            .author
+            .iter()
+            .map(|author| common::encode_html(author, self.escape_html).into_owned())
+            .collect();
+        let date_published = self.metadata.date_published;
+        let date_modified = self.metadata.date_modified.unwrap_or_else(chrono::Utc::now);
+        let uuid = self.metadata.uuid.unwrap_or_default();
+        let data = upon::value! {
+            title: common::encode_html(self.main_title(), self.escape_html),
+            author_names: authors.join(", "),
+            authors: authors,
+            publisher: self
+                .metadata
+                .publisher
+                .as_ref()
+                .map(|publisher| common::encode_html(publisher, self.escape_html)),
+            license: self
+                .metadata
+                .license
+                .as_ref()
+                .map(|license| common::encode_html(license, self.escape_html)),
+            generator: common::encode_html(&self.metadata.generator, self.escape_html),
+            date_published: date_published.map(|date| date.format("%Y-%m-%d").to_string()),
+            date_modified: date_modified.format("%Y-%m-%d").to_string(),
+            year: date_published.unwrap_or(date_modified).format("%Y").to_string(),
+            identifier: uuid::fmt::Urn::from_uuid(uuid).to_string(),
+            labels: upon::value! {
+                published_by: common::encode_html(self.label(Label::PublishedBy), self.escape_html),
+                by: common::encode_html(self.label(Label::By), self.escape_html),
+                generated_with: common::encode_html(self.label(Label::GeneratedWith), self.escape_html),
+            },
+        };
+        let template = match self.generated_page_templates.get(&page) {
+            Some(template) => template,
+            None => match page {
+                GeneratedPage::TitlePage => &*templates::TITLE_PAGE_XHTML,
+                GeneratedPage::Copyright => &*templates::COPYRIGHT_XHTML,
+                GeneratedPage::Colophon => &*templates::COLOPHON_XHTML,
+            },
+        };
+        template
+            .render(&templates::ENGINE, &data)
+            .to_string()
+            .map_err(|e| crate::Error::TemplateError {
+                msg: format!("error rendering {} template", page.file()),
+                cause: e.into(),
+            })
+    }
+
+    /// Adds the generated cover page as the first file, if there is a cover image
+    fn add_cover_page(&mut self, linear: bool) -> Result<()> {
+        let Some(image) = self.files.iter().find(|file| file.cover) else {
+            return Ok(());
+        };
+        let bytes = match image.source {
+            Some(ref source) => source.read_to_vec().map_err(|e| crate::Error::IoError {
+                msg: format!("could not read file '{}'", image.file),
+                cause: e,
+            })?,
+            None => vec![],
+        };
+        let size = imagesize::blob_size(&bytes).ok();
+        if size.is_none() {
+            log::warn!("could not get the size of cover image {}", image.file);
+        }
+        let title = self.label(Label::Cover).to_string();
+        let data = upon::value! {
+            has_size: size.is_some(),
+            width: size.map(|size| size.width).unwrap_or_default(),
+            height: size.map(|size| size.height).unwrap_or_default(),
+            image_attr: html_escape::encode_double_quoted_attribute(&common::encode_href(&image.file)),
+            title_attr: html_escape::encode_double_quoted_attribute(&title),
+        };
+        let body = templates::COVER_XHTML
+            .render(&templates::ENGINE, &data)
+            .to_string()
+            .map_err(|e| crate::Error::TemplateError {
+                msg: "error rendering cover.xhtml template".to_string(),
+                cause: e.into(),
+            })?;
+
+        self.check_href("cover.xhtml")?;
+        let mut file = Content::new("cover.xhtml", "application/xhtml+xml");
+        file.source = Some(ContentSource::bytes(body));
+        file.fragment = true;
+        file.page_title = title.to_string();
+        file.itemref = true;
+        file.linear = linear;
+        file.svg = size.is_some();
+        file.reftype = Some(ReferenceType::Cover);
+        file.title = title.to_string();
+        self.insert_file(0, file)
+    }
+
+    /// Adds the endnotes document at the end of the spine
+    fn add_endnotes(&mut self) -> Result<()> {
+        let title = self.label(Label::Notes).to_string();
+        let heading = common::encode_html(&title, self.escape_html);
+        let body = notes::render_endnotes(self.version, &heading, self.notes.iter());
+        self.check_href(notes::ENDNOTES_FILE)?;
+        let mut file = Content::new(notes::ENDNOTES_FILE, "application/xhtml+xml");
+        file.source = Some(ContentSource::bytes(body));
+        file.fragment = true;
+        file.page_title = title.to_string();
+        file.itemref = true;
+        file.reftype = Some(ReferenceType::Notes);
+        file.title = title.to_string();
+        self.insert_file(self.files.len(), file)
+    }
+
+    /// Adds the glossary document at the end of the spine
+    fn add_glossary(&mut self) -> Result<()> {
+        let title = self.label(Label::Glossary).to_string();
+        let heading = common::encode_html(&title, self.escape_html);
+        let body =
+            glossary::render_glossary(self.version, &heading, &self.glossary, self.escape_html);
+        self.check_href(glossary::GLOSSARY_FILE)?;
+        let mut file = Content::new(glossary::GLOSSARY_FILE, "application/xhtml+xml");
+        file.source = Some(ContentSource::bytes(body));
+        file.fragment = true;
+        file.page_title = title.to_string();
+        file.itemref = true;
+        file.reftype = Some(ReferenceType::Glossary);
+        file.title = title.to_string();
+        file.property = Some("glossary");
+        self.insert_file(self.files.len(), file)
+    }
+
+    /// Adds the dictionary document at the end of the spine, and its search key map
+    fn add_dictionary(&mut self) -> Result<()> {
+        let title = self.label(Label::Dictionary).to_string();
+        let heading = common::encode_html(&title, self.escape_html);
+        let body = glossary::render_dictionary(
+            self.version,
+            &heading,
+            &self.dictionary,
+            self.escape_html,
+        );
+        self.check_href(glossary::DICTIONARY_FILE)?;
+        let mut file = Content::new(glossary::DICTIONARY_FILE, "application/xhtml+xml");
+        file.source = Some(ContentSource::bytes(body));
+        file.fragment = true;
+        file.page_title = title.to_string();
+        file.itemref = true;
+        file.reftype = Some(ReferenceType::Glossary);
+        file.title = title.to_string();
+        file.property = Some("dictionary");
+        self.insert_file(self.files.len(), file)?;
+        if self.version == EpubVersion::V30 {
+            let map = glossary::render_search_key_map(&self.metadata.lang, &self.dictionary);
+            self.check_href(glossary::SEARCH_KEY_MAP_FILE)?;
+            let mut file = Content::new(
+                glossary::SEARCH_KEY_MAP_FILE,
+                "application/vnd.epub.search-key-map+xml",
+            );
+            file.source = Some(ContentSource::bytes(map));
+            file.property = Some("search-key-map");
+            self.insert_file(self.files.len(), file)?;
+        }
         Ok(())
     }
 
+    /// Adds the bibliography document at the end of the spine
+    fn add_bibliography(&mut self) -> Result<()> {
+        let title = self.label(Label::Bibliography).to_string();
+        let body = bibliography::render_bibliography(
+            self.version,
+            &common::encode_html(&title, self.escape_html),
+            &self.citations,
+            self.citation_formatter.as_ref(),
+            self.escape_html,
+        );
+        self.check_href(bibliography::BIBLIOGRAPHY_FILE)?;
+        let mut file = Content::new(bibliography::BIBLIOGRAPHY_FILE, "application/xhtml+xml");
+        file.source = Some(ContentSource::bytes(body));
+        file.fragment = true;
+        file.page_title = title.to_string();
+        file.itemref = true;
+        file.reftype = Some(ReferenceType::Bibliography);
+        file.title = title.to_string();
+        self.insert_file(self.files.len(), file)
+    }
+
+    /// Adds the index document at the end of the spine
+    fn add_index(&mut self) -> Result<()> {
+        let title = self.label(Label::Index).to_string();
+        let heading = common::encode_html(&title, self.escape_html);
+        let body = index::render_index(self.version, &heading, &self.index, self.escape_html);
+        self.check_href(index::INDEX_FILE)?;
+        let mut file = Content::new(index::INDEX_FILE, "application/xhtml+xml");
+        file.source = Some(ContentSource::bytes(body));
+        file.fragment = true;
+        file.page_title = title.to_string();
+        file.itemref = true;
+        file.reftype = Some(ReferenceType::Index);
+        file.title = title.to_string();
+        file.property = Some("index");
+        self.insert_file(self.files.len(), file)
+    }
+
+    /// Value of the `dir` attribute of generated documents, empty for the default direction
+    fn dir_attr(&self) -> &'static str {
+        match self.metadata.direction {
+            PageDirection::Rtl => "rtl",
+            PageDirection::Ltr => "",
+        }
+    }
+
+    /// Wraps the body fragment of a content file in a complete XHTML page
+    fn render_page(&self, file: &Content, body: &str) -> Result<Vec<u8>> {
+        let title = if file.page_title.is_empty() {
+            self.main_title()
+        } else {
+            &file.page_title
+        };
+        let stylesheet = common::relative_href(&file.file, "stylesheet.css");
+        let data = upon::value! {
+            lang_attr: html_escape::encode_double_quoted_attribute(&self.metadata.lang),
+            dir_attr: self.dir_attr(),
+            generator_attr: html_escape::encode_double_quoted_attribute(&self.metadata.generator),
+            title: common::encode_html(title, self.escape_html),
+            stylesheet_attr: html_escape::encode_double_quoted_attribute(&stylesheet),
+            body: body, // Not escaped: XML content
+        };
+
+        let mut res: Vec<u8> = vec![];
+        match (&self.page_template, self.version) {
+            (Some(template), _) => template.render(&templates::ENGINE, &data).to_writer(&mut res),
+            (None, EpubVersion::V20) => templates::v2::PAGE_XHTML.render(&Engine::new(), &data).to_writer(&mut res),
+            (None, EpubVersion::V30) => templates::v3::PAGE_XHTML.render(&Engine::new(), &data).to_writer(&mut res),
+        }
+        .map_err(|e| crate::Error::TemplateError {
+            msg: format!("error rendering page template for '{}'", file.file),
+            cause: e.into(),
+        })?;
+        Ok(res)
+    }
+
     /// Render content.opf file
     fn render_opf(&mut self) -> Result<Vec<u8>> {
         log::debug!("render_opf...");
@@ -600,12 +2077,29 @@ impl<Z: Zip> EpubBuilder<Z> {
                 common::encode_html(desc, self.escape_html),
             ));
         }
+        for (desc, lang) in &self.metadata.localized_descriptions {
+            optional.push(format!(
+                "<dc:description xml:lang=\"{}\">{}</dc:description>",
+                html_escape::encode_double_quoted_attribute(lang),
+                common::encode_html(desc, self.escape_html),
+            ));
+        }
         for subject in &self.metadata.subject {
             optional.push(format!(
                 "<dc:subject>{}</dc:subject>",
                 common::encode_html(subject, self.escape_html),
             ));
         }
+        for (i, subject) in self.metadata.structured_subjects.iter().enumerate() {
+            let lines = subject::render_subject(self.version, subject, i + 1, self.escape_html);
+            optional.extend(lines);
+        }
+        if let Some(ref publisher) = self.metadata.publisher {
+            optional.push(format!(
+                "<dc:publisher>{}</dc:publisher>",
+                common::encode_html(publisher, self.escape_html),
+            ));
+        }
         if let Some(ref rights) = self.metadata.license {
             optional.push(format!(
                 "<dc:rights>{}</dc:rights>",
@@ -623,31 +2117,48 @@ impl<Z: Zip> EpubBuilder<Z> {
         let date_modified = self
             .metadata
             .date_modified
+            .or_else(|| self.reproducible.then(reproducible_timestamp))
             .unwrap_or_else(chrono::Utc::now)
             .format("%Y-%m-%dT%H:%M:%SZ");
         let date_published = self
             .metadata
             .date_published
             .map(|date| date.format("%Y-%m-%dT%H:%M:%SZ"));
-        let uuid = uuid::fmt::Urn::from_uuid(self.metadata.uuid.unwrap_or_else(uuid::Uuid::new_v4))
-            .to_string();
+        let uuid = self.metadata.uuid.unwrap_or_else(|| {
+            if self.reproducible {
+                self.stable_uuid()
+            } else {
+                uuid::Uuid::new_v4()
+            }
+        });
+        let uuid = uuid::fmt::Urn::from_uuid(uuid).to_string();
 
         let mut items: Vec<String> = Vec::new();
         let mut itemrefs: Vec<String> = Vec::new();
         let mut guide: Vec<String> = Vec::new();
 
         for content in &self.files {
-            let id = if content.cover {
-                String::from("cover-image")
-            } else {
-                to_id(&content.file)
-            };
-            let properties = match (self.version, content.cover) {
-                (EpubVersion::V30, true) => "properties=\"cover-image\" ",
-                _ => "",
+            let id = &content.id;
+            let properties: Vec<&str> = [
+                (content.cover, "cover-image"),
+                (content.svg, "svg"),
+                (content.scripted, "scripted"),
+            ]
+            .into_iter()
+            .filter_map(|(set, property)| set.then_some(property))
+            .chain(content.property)
+            .collect();
+            let properties = match self.version {
+                EpubVersion::V30 if !properties.is_empty() => {
+                    format!("properties=\"{}\" ", properties.join(" "))
+                }
+                _ => String::new(),
             };
             if content.cover {
-                optional.push("<meta name=\"cover\" content=\"cover-image\"/>".to_string());
+                optional.push(format!(
+                    "<meta name=\"cover\" content=\"{id}\"/>",
+                    id = html_escape::encode_double_quoted_attribute(id),
+                ));
             }
             log::debug!("id={:?}, mime={:?}", id, content.mime);
             items.push(format!(
@@ -656,14 +2167,15 @@ impl<Z: Zip> EpubBuilder<Z> {
                 properties = properties, // Not escaped: XML attributes above
                 mime = html_escape::encode_double_quoted_attribute(&content.mime),
                 id = html_escape::encode_double_quoted_attribute(&id),
-                // in the zip the path is always with forward slashes, on windows it is with backslashes
-                href =
-                    html_escape::encode_double_quoted_attribute(&content.file.replace('\\', "/")),
+                href = html_escape::encode_double_quoted_attribute(&common::encode_href(
+                    &content.file
+                )),
             ));
             if content.itemref {
                 itemrefs.push(format!(
-                    "<itemref idref=\"{id}\"/>",
+                    "<itemref idref=\"{id}\"{linear}/>",
                     id = html_escape::encode_double_quoted_attribute(&id),
+                    linear = if content.linear { "" } else { " linear=\"no\"" }, // Not escaped: static string
                 ));
             }
             if let Some(reftype) = content.reftype {
@@ -692,11 +2204,43 @@ impl<Z: Zip> EpubBuilder<Z> {
                     "<reference type=\"{reftype}\" title=\"{title}\" href=\"{href}\"/>",
                     reftype = html_escape::encode_double_quoted_attribute(&reftype),
                     title = html_escape::encode_double_quoted_attribute(&content.title),
-                    href = html_escape::encode_double_quoted_attribute(&content.file),
+                    href = html_escape::encode_double_quoted_attribute(&common::encode_href(
+                    &content.file
+                )),
                 ));
             }
         }
 
+        let mut collections: Vec<String> = Vec::new();
+        if !self.glossary.is_empty() {
+            collections.push(format!(
+                "<collection role=\"glossary\">\n  <link href=\"{}\"/>\n</collection>",
+                glossary::GLOSSARY_FILE
+            ));
+        }
+        if !self.dictionary.is_empty() {
+            let source = &self.metadata.lang;
+            let target = self.dictionary_target_language.as_ref().unwrap_or(source);
+            collections.push(format!(
+                "<collection role=\"dictionary\">\n  \
+                 <metadata xmlns:dc=\"http://purl.org/dc/elements/1.1/\">\n    \
+                 <dc:type>dictionary</dc:type>\n    \
+                 <meta property=\"source-language\">{}</meta>\n    \
+                 <meta property=\"target-language\">{}</meta>\n  </metadata>\n  \
+                 <link href=\"{}\"/>\n  <link href=\"{}\"/>\n</collection>",
+                html_escape::encode_text(source),
+                html_escape::encode_text(target),
+                glossary::SEARCH_KEY_MAP_FILE,
+                glossary::DICTIONARY_FILE
+            ));
+        }
+        if !self.index.is_empty() {
+            collections.push(format!(
+                "<collection role=\"index\">\n  <link href=\"{}\"/>\n</collection>",
+                index::INDEX_FILE
+            ));
+        }
+
         let data = {
             let mut authors: Vec<_> = vec![];
             for (i, author) in self.metadata.author.iter().enumerate() {
@@ -710,16 +2254,20 @@ impl<Z: Zip> EpubBuilder<Z> {
                 author: authors,
                 lang: html_escape::encode_text(&self.metadata.lang),
                 direction: self.metadata.direction.to_string(),
-                title: common::encode_html(&self.metadata.title, self.escape_html),
+                titles: common::indent(
+                    title::render_titles(self.version, &self.titles(), self.escape_html),
+                    2,
+                ), // Not escaped: XML content
                 generator_attr: html_escape::encode_double_quoted_attribute(&self.metadata.generator),
-                toc_name: common::encode_html(&self.metadata.toc_name, self.escape_html),
-                toc_name_attr: html_escape::encode_double_quoted_attribute(&self.metadata.toc_name),
+                toc_name: common::encode_html(self.label(Label::Toc), self.escape_html),
+                toc_name_attr: html_escape::encode_double_quoted_attribute(self.label(Label::Toc)),
                 optional: common::indent(optional.join("\n"), 2),
                 items: common::indent(items.join("\n"), 2), // Not escaped: XML content
                 itemrefs: common::indent(itemrefs.join("\n"), 2), // Not escaped: XML content
                 date_modified: html_escape::encode_text(&date_modified.to_string()),
                 uuid: html_escape::encode_text(&uuid),
                 guide: common::indent(guide.join("\n"), 2), // Not escaped: XML content
+                collections: common::indent(collections.join("\n"), 1), // Not escaped: XML content
                 date_published: if let Some(date) = date_published { date.to_string() } else { String::new() },
             }
         };
@@ -738,6 +2286,16 @@ impl<Z: Zip> EpubBuilder<Z> {
         Ok(res)
     }
 
+    /// Derive an UUID from the title and authors, for reproducible builds
+    fn stable_uuid(&self) -> uuid::Uuid {
+        let mut name = self.main_title().to_string();
+        for author in &self.metadata.author {
+            name.push('\n');
+            name.push_str(author);
+        }
+        uuid::Uuid::new_v5(&uuid::Uuid::NAMESPACE_URL, name.as_bytes())
+    }
+
     /// Render toc.ncx
     fn render_toc(&mut self) -> Result<Vec<u8>> {
         let mut nav_points = String::new();
@@ -745,7 +2303,9 @@ impl<Z: Zip> EpubBuilder<Z> {
         nav_points.push_str(&self.toc.render_epub(self.escape_html));
 
         let data = upon::value! {
-            toc_name: common::encode_html(&self.metadata.toc_name, self.escape_html),
+            doc_title: common::encode_html(self.doc_title(), self.escape_html),
+            lang_attr: html_escape::encode_double_quoted_attribute(&self.metadata.lang),
+            dir_attr: self.dir_attr(),
             nav_points: nav_points
         };
         let mut res: Vec<u8> = vec![];
@@ -791,7 +2351,9 @@ impl<Z: Zip> EpubBuilder<Z> {
                             "<li><a epub:type=\"{reftype}\" href=\"{href}\">\
                                 {title}</a></li>",
                             reftype = html_escape::encode_double_quoted_attribute(&reftype),
-                            href = html_escape::encode_double_quoted_attribute(&file.file),
+                            href = html_escape::encode_double_quoted_attribute(
+                                &common::encode_href(&file.file)
+                            ),
                             title = common::encode_html(&file.title, self.escape_html),
                         ));
                     }
@@ -801,12 +2363,16 @@ impl<Z: Zip> EpubBuilder<Z> {
 
         let data = upon::value! {
             content: content, // Not escaped: XML content
-            toc_name: common::encode_html(&self.metadata.toc_name, self.escape_html),
+            toc_name: common::encode_html(self.label(Label::Toc), self.escape_html),
+            doc_title: common::encode_html(self.doc_title(), self.escape_html),
+            lang_attr: html_escape::encode_double_quoted_attribute(&self.metadata.lang),
+            dir_attr: self.dir_attr(),
             generator_attr: html_escape::encode_double_quoted_attribute(&self.metadata.generator),
             landmarks: if !landmarks.is_empty() {
                 common::indent(
                     format!(
-                        "<ol>\n{}\n</ol>",
+                        "<h2>{}</h2>\n<ol>\n{}\n</ol>",
+                        common::encode_html(self.label(Label::Landmarks), self.escape_html),
                         common::indent(landmarks.join("\n"), 1), // Not escaped: XML content
                     ),
                     2,
@@ -829,6 +2395,198 @@ impl<Z: Zip> EpubBuilder<Z> {
     }
 }
 
+#[cfg(feature = "async")]
+impl<Z: crate::zip::AsyncZip> EpubBuilder<Z> {
+    /// Asynchronous version of `add_resource`
+    ///
+    /// The content is read asynchronously in memory before being added.
+    pub async fn add_resource_async<R, P, S>(
+        &mut self,
+        path: P,
+        content: R,
+        mime_type: S,
+    ) -> Result<&mut Self>
+    where
+        R: tokio::io::AsyncRead + Unpin,
+        P: AsRef<Path>,
+        S: Into<String>,
+    {
+        let bytes = read_async(path.as_ref(), content).await?;
+        self.add_resource(path, ContentSource::Bytes(bytes), mime_type)
+    }
+
+    /// Asynchronous version of `add_cover_image`
+    ///
+    /// The content is read asynchronously in memory before being added.
+    pub async fn add_cover_image_async<R, P, S>(
+        &mut self,
+        path: P,
+        content: R,
+        mime_type: S,
+    ) -> Result<&mut Self>
+    where
+        R: tokio::io::AsyncRead + Unpin,
+        P: AsRef<Path>,
+        S: Into<String>,
+    {
+        let bytes = read_async(path.as_ref(), content).await?;
+        self.add_cover_image(path, ContentSource::Bytes(bytes), mime_type)
+    }
+
+    /// Asynchronous version of `add_content`
+    ///
+    /// The content is read asynchronously in memory before being added.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use epub_builder::{EpubBuilder, ZipLibrary, EpubContent};
+    /// # async fn run() -> epub_builder::Result<()> {
+    /// let mut builder = EpubBuilder::new(ZipLibrary::new()?)?;
+    /// let chapter = tokio::fs::File::open("chapter_1.xhtml").await?;
+    /// builder
+    ///     .add_content_async(EpubContent::new("chapter_1.xhtml", chapter).title("Chapter 1"))
+    ///     .await?;
+    /// let output = tokio::fs::File::create("book.epub").await?;
+    /// builder.generate_async(output).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn add_content_async<R>(&mut self, content: EpubContent<R>) -> Result<&mut Self>
+    where
+        R: tokio::io::AsyncRead + Unpin,
+    {
+        let bytes = read_async(Path::new(&content.toc.url), content.content).await?;
+        self.add_content(EpubContent {
+            toc: content.toc,
+            content: ContentSource::Bytes(bytes),
+            reftype: content.reftype,
+            id: content.id,
+            fragment: content.fragment,
+            scripted: content.scripted,
+            xml_prolog: content.xml_prolog,
+        })
+    }
+
+    /// Asynchronous version of `generate`
+    ///
+    /// The EPUB file is generated in memory, then written asynchronously.
+    pub async fn generate_async<W>(self, to: W) -> Result<()>
+    where
+        W: tokio::io::AsyncWrite + Unpin,
+    {
+        self.into_zip()?.generate_async(to).await
+    }
+}
+
+/// Reads asynchronously all the content of a file to be added
+#[cfg(feature = "async")]
+async fn read_async<R: tokio::io::AsyncRead + Unpin>(
+    path: &Path,
+    mut content: R,
+) -> Result<Vec<u8>> {
+    use tokio::io::AsyncReadExt;
+
+    let mut bytes = vec![];
+    content
+        .read_to_end(&mut bytes)
+        .await
+        .map_err(|e| crate::Error::IoError {
+            msg: format!("could not read file '{}'", path.display()),
+            cause: e,
+        })?;
+    Ok(bytes)
+}
+
+/// Returns the XML declaration and the doctype of XHTML documents for `version`
+fn xml_prolog(version: EpubVersion) -> String {
+    let doctype = match version {
+        EpubVersion::V20 => {
+            "<!DOCTYPE html PUBLIC \"-//W3C//DTD XHTML 1.1//EN\" \
+             \"http://www.w3.org/TR/xhtml11/DTD/xhtml11.dtd\">"
+        }
+        EpubVersion::V30 => "<!DOCTYPE html>",
+    };
+    format!("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n{doctype}\n")
+}
+
+/// Creates the file for a XHTML content added to the spine
+fn content_file<R: IntoContentSource>(mut content: EpubContent<R>) -> Result<Content> {
+    let path = Path::new(&content.toc.url);
+    let href = common::normalize_path(path)?;
+    let source = read_source(path, content.content)?;
+    normalize_toc_urls(&mut content.toc)?;
+    let mut file = Content::new(href, "application/xhtml+xml");
+    file.source = Some(source);
+    file.id = content.id.unwrap_or_default();
+    file.fragment = content.fragment;
+    file.scripted = content.scripted;
+    file.xml_prolog = content.xml_prolog;
+    if file.fragment {
+        file.page_title = content
+            .toc
+            .raw_title
+            .clone()
+            .unwrap_or_else(|| content.toc.title.clone());
+    }
+    file.itemref = true;
+    file.reftype = content.reftype;
+    if file.reftype.is_some() {
+        file.title = content.toc.title.clone();
+    }
+    if !content.toc.title.is_empty() {
+        file.toc = Some(content.toc);
+    }
+    Ok(file)
+}
+
+/// Normalizes the links of a table of contents element and of its children
+fn normalize_toc_urls(element: &mut TocElement) -> Result<()> {
+    element.url = common::normalize_url(&element.url)?;
+    for child in &mut element.children {
+        normalize_toc_urls(child)?;
+    }
+    Ok(())
+}
+
+/// Reads all the content of a file as a string
+fn read_string<R: io::Read>(path: &str, mut content: R) -> Result<String> {
+    let mut bytes = vec![];
+    content
+        .read_to_end(&mut bytes)
+        .map_err(|e| crate::Error::IoError {
+            msg: format!("could not read file '{}'", path),
+            cause: e,
+        })?;
+    String::from_utf8(bytes).map_err(|e| crate::Error::IoError {
+        msg: format!("could not read file '{}'", path),
+        cause: io::Error::new(io::ErrorKind::InvalidData, e),
+    })
+}
+
+/// Converts the content of a file to a source, reading it if it is not already one
+fn read_source<R: IntoContentSource>(path: &Path, content: R) -> Result<ContentSource> {
+    content
+        .into_content_source()
+        .map_err(|e| crate::Error::IoError {
+            msg: format!("could not read file '{}'", path.display()),
+            cause: e,
+        })
+}
+
+/// Timestamp used in reproducible mode: `SOURCE_DATE_EPOCH` if it is set, else
+/// the earliest date that can be stored in a zip file.
+fn reproducible_timestamp() -> chrono::DateTime<chrono::Utc> {
+    std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.trim().parse::<i64>().ok())
+        .and_then(|epoch| chrono::DateTime::from_timestamp(epoch, 0))
+        .unwrap_or_else(|| {
+            chrono::DateTime::from_timestamp(315_532_800, 0) // 1980-01-01T00:00:00Z
+                .expect("valid timestamp")
+        })
+}
+
 // The actual rules for ID are here - https://www.w3.org/TR/xml-names11/#NT-NCNameChar
 // Ordering to to look as similar as possible to the W3 Recommendation ruleset
 // Slightly more permissive, there are some that are invalid start chars, but this is ok.
@@ -856,7 +2614,683 @@ fn is_id_char(c: char) -> bool {
         || ('\u{203F}'..='\u{2040}').contains(&c)
 }
 
+/// Tells whether an id given by the caller is a valid XML id
+fn is_valid_id(id: &str) -> bool {
+    match id.chars().next() {
+        Some(first) if first.is_ascii_digit() || first == '-' || first == '.' => false,
+        Some(_) => id.chars().all(is_id_char),
+        None => false,
+    }
+}
+
 // generate an id compatible string, replacing all none ID chars to underscores
 fn to_id(s: &str) -> String {
     "id_".to_string() + &s.replace(|c: char| !is_id_char(c), "_")
 }
+
+#[cfg(feature = "libzip")]
+#[test]
+fn reproducible_output() {
+    fn build() -> Vec<u8> {
+        let mut builder = EpubBuilder::new(crate::ZipLibrary::new().unwrap()).unwrap();
+        builder
+            .reproducible(true)
+            .epub_version(EpubVersion::V30)
+            .metadata("title", "Reproducible")
+            .unwrap()
+            .metadata("author", "Ann 'Onymous")
+            .unwrap()
+            .add_content(EpubContent::new("chapter.xhtml", b"Chapter".as_ref()).title("Chapter"))
+            .unwrap();
+        let mut output = vec![];
+        builder.generate(&mut output).unwrap();
+        output
+    }
+
+    // Nothing depends on the time of generation
+    let output = build();
+    assert_eq!(output, build());
+    let timestamp = reproducible_timestamp();
+    let expected = crate::zip_library::zip_date_time(timestamp);
+    let mut archive = libzip::ZipArchive::new(io::Cursor::new(output)).unwrap();
+    for i in 0..archive.len() {
+        assert_eq!(archive.by_index(i).unwrap().last_modified(), Some(expected));
+    }
+    let mut opf = String::new();
+    io::Read::read_to_string(&mut archive.by_name("OEBPS/content.opf").unwrap(), &mut opf).unwrap();
+    let modified = timestamp.format("%Y-%m-%dT%H:%M:%SZ").to_string();
+    assert!(opf.contains(&format!("<meta property=\"dcterms:modified\">{modified}</meta>")));
+}
+
+#[cfg(feature = "zip-stream")]
+#[test]
+fn content_read_on_generation() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    let reads = Arc::new(AtomicUsize::new(0));
+    let counter = Arc::clone(&reads);
+    let mut builder = EpubBuilder::new(crate::ZipMemory::new()).unwrap();
+    builder
+        .add_content(EpubContent::new(
+            "chapter.xhtml",
+            ContentSource::reader(move || {
+                counter.fetch_add(1, Ordering::SeqCst);
+                Ok("Chapter".as_bytes())
+            }),
+        ))
+        .unwrap();
+    assert_eq!(reads.load(Ordering::SeqCst), 0);
+
+    let zip = builder.into_zip().unwrap();
+    assert_eq!(reads.load(Ordering::SeqCst), 1);
+    assert_eq!(zip.get_str("OEBPS/chapter.xhtml"), Some("Chapter"));
+}
+
+#[cfg(all(test, feature = "async", feature = "zip-stream"))]
+#[tokio::test]
+async fn generate_async() {
+    let mut builder = EpubBuilder::new(crate::ZipMemory::new()).unwrap();
+    builder
+        .add_content_async(EpubContent::new("chapter.xhtml", b"Chapter".as_ref()).title("Chapter"))
+        .await
+        .unwrap();
+    let future = builder.generate_async(vec![]);
+    fn assert_send<T: Send>(_: &T) {}
+    assert_send(&future);
+    future.await.unwrap();
+}
+
+#[cfg(feature = "zip-stream")]
+#[test]
+fn reorder_spine() {
+    let mut builder = EpubBuilder::new(crate::ZipMemory::new()).unwrap();
+    builder
+        .epub_version(EpubVersion::V30)
+        .add_content(EpubContent::new("one.xhtml", b"".as_ref()).title("One"))
+        .unwrap()
+        .add_content(EpubContent::new("two.xhtml", b"".as_ref()).title("Two"))
+        .unwrap()
+        .add_content(EpubContent::new("three.xhtml", b"".as_ref()).title("Three"))
+        .unwrap()
+        .add_resource("image.png", b"".as_ref(), "image/png")
+        .unwrap();
+    builder
+        .move_after("one.xhtml", "three.xhtml")
+        .unwrap()
+        .remove("two.xhtml")
+        .unwrap()
+        .insert_content_before(
+            "three.xhtml",
+            EpubContent::new("zero.xhtml", b"".as_ref()).title("Zero"),
+        )
+        .unwrap();
+    assert!(builder.remove("two.xhtml").is_err());
+    assert!(builder.move_before("one.xhtml", "missing.xhtml").is_err());
+    assert_eq!(
+        builder.spine().collect::<Vec<_>>(),
+        ["zero.xhtml", "three.xhtml", "one.xhtml"]
+    );
+    assert_eq!(builder.resources().collect::<Vec<_>>(), ["image.png"]);
+
+    let zip = builder.into_zip().unwrap();
+    let nav = zip.get_str("OEBPS/nav.xhtml").unwrap();
+    let zero = nav.find("Zero").unwrap();
+    let three = nav.find("Three").unwrap();
+    let one = nav.find("One").unwrap();
+    assert!(zero < three && three < one);
+    assert!(!nav.contains("Two"));
+    assert!(!zip.files().any(|path| path.ends_with("two.xhtml")));
+}
+
+#[cfg(feature = "zip-stream")]
+#[test]
+fn deduplicate_resources() {
+    let mut builder = EpubBuilder::new(crate::ZipMemory::new()).unwrap();
+    builder
+        .deduplicate_resources(true)
+        .add_resource("a/logo.png", b"logo".as_ref(), "image/png")
+        .unwrap()
+        .add_resource("b/logo.png", b"logo".as_ref(), "image/png")
+        .unwrap()
+        .add_resource("c/logo.png", ContentSource::bytes("logo"), "image/png")
+        .unwrap()
+        .add_resource("other.png", b"other".as_ref(), "image/png")
+        .unwrap()
+        .add_resource("logo.txt", b"logo".as_ref(), "text/plain")
+        .unwrap();
+    assert_eq!(builder.canonical_href("a/logo.png"), Some("a/logo.png"));
+    assert_eq!(builder.canonical_href("c/logo.png"), Some("a/logo.png"));
+    assert_eq!(builder.canonical_href("missing.png"), None);
+    assert_eq!(
+        builder.deduplication_report(),
+        DeduplicationReport {
+            duplicates: 2,
+            bytes_saved: 8
+        }
+    );
+
+    // Options that an alias could not honour keep the resource apart, and the stylesheet
+    // is always stored since generated pages link to it
+    builder
+        .add_resource_with_options(
+            "d/logo.png",
+            b"logo".as_ref(),
+            "image/png",
+            ResourceOptions::new().id("logo"),
+        )
+        .unwrap()
+        .add_resource_with_options(
+            "e/logo.png",
+            b"logo".as_ref(),
+            "image/png",
+            ResourceOptions::new().compression(Compression::Stored),
+        )
+        .unwrap()
+        .stylesheet(b"logo".as_ref())
+        .unwrap();
+    assert_eq!(builder.canonical_href("d/logo.png"), Some("d/logo.png"));
+    assert_eq!(builder.manifest_id("d/logo.png"), Some("logo"));
+    assert_eq!(builder.canonical_href("e/logo.png"), Some("e/logo.png"));
+    assert_eq!(builder.canonical_href("stylesheet.css"), Some("stylesheet.css"));
+    assert_eq!(builder.deduplication_report().duplicates, 2);
+
+    // Removing the canonical resource keeps it under the href of a duplicate
+    builder.remove("a/logo.png").unwrap().remove("c/logo.png").unwrap();
+    assert_eq!(builder.canonical_href("a/logo.png"), None);
+    assert_eq!(builder.canonical_href("b/logo.png"), Some("b/logo.png"));
+    assert_eq!(builder.canonical_href("c/logo.png"), None);
+    assert_eq!(builder.deduplication_report(), DeduplicationReport::default());
+
+    let zip = builder.into_zip().unwrap();
+    assert!(zip.get("OEBPS/a/logo.png").is_none());
+    assert!(zip.get("OEBPS/b/logo.png").is_some());
+    assert!(zip.get("OEBPS/other.png").is_some());
+    assert!(zip.get("OEBPS/logo.txt").is_some());
+    assert!(zip.get("OEBPS/d/logo.png").is_some());
+    assert!(zip.get("OEBPS/e/logo.png").is_some());
+    assert_eq!(zip.get_str("OEBPS/stylesheet.css"), Some("logo"));
+}
+
+#[cfg(feature = "zip-stream")]
+#[test]
+fn normalize_and_check_paths() {
+    let mut builder = EpubBuilder::new(crate::ZipMemory::new()).unwrap();
+    builder
+        .add_content(EpubContent::new("./text/../chapter 1.xhtml", b"".as_ref()).title("One"))
+        .unwrap()
+        .add_resource("images\\été.png", b"".as_ref(), "image/png")
+        .unwrap();
+    assert_eq!(builder.spine().collect::<Vec<_>>(), ["chapter 1.xhtml"]);
+    assert!(matches!(
+        builder.add_content(EpubContent::new("chapter 1.xhtml", b"".as_ref())),
+        Err(crate::Error::DuplicatePath(_))
+    ));
+    assert!(matches!(
+        builder.add_resource("Images/Été.png", b"".as_ref(), "image/png"),
+        Err(crate::Error::PathCollision { .. })
+    ));
+    assert!(matches!(
+        builder.add_resource("nav.xhtml", b"".as_ref(), "application/xhtml+xml"),
+        Err(crate::Error::DuplicatePath(_))
+    ));
+    assert!(matches!(
+        builder.add_resource("../outside.png", b"".as_ref(), "image/png"),
+        Err(crate::Error::InvalidPath(_))
+    ));
+
+    let zip = builder.into_zip().unwrap();
+    assert!(zip.get("OEBPS/chapter 1.xhtml").is_some());
+    assert!(zip.get("OEBPS/images/été.png").is_some());
+    let opf = zip.get_str("OEBPS/content.opf").unwrap();
+    assert!(opf.contains("href=\"chapter%201.xhtml\""));
+    assert!(opf.contains("href=\"images/%C3%A9t%C3%A9.png\""));
+    let ncx = zip.get_str("OEBPS/toc.ncx").unwrap();
+    assert!(ncx.contains("src=\"chapter%201.xhtml\""));
+
+    // The inline table of contents can't be written over a file added before it
+    let mut builder = EpubBuilder::new(crate::ZipMemory::new()).unwrap();
+    builder
+        .add_content(EpubContent::new("toc.xhtml", b"".as_ref()))
+        .unwrap()
+        .inline_toc();
+    assert!(matches!(
+        builder.into_zip(),
+        Err(crate::Error::DuplicatePath(_))
+    ));
+}
+
+#[cfg(feature = "zip-stream")]
+#[test]
+fn unique_manifest_ids() {
+    let mut builder = EpubBuilder::new(crate::ZipMemory::new()).unwrap();
+    builder
+        .add_content(EpubContent::new("a b.xhtml", b"".as_ref()))
+        .unwrap()
+        .add_content(EpubContent::new("a_b.xhtml", b"".as_ref()))
+        .unwrap()
+        .add_resource_with_options(
+            "logo.png",
+            b"".as_ref(),
+            "image/png",
+            ResourceOptions::new().id("logo"),
+        )
+        .unwrap();
+    assert!(matches!(
+        builder.add_content(EpubContent::new("c.xhtml", b"".as_ref()).id("logo")),
+        Err(crate::Error::DuplicateId(_))
+    ));
+    assert!(matches!(
+        builder.add_content(EpubContent::new("c.xhtml", b"".as_ref()).id("nav")),
+        Err(crate::Error::DuplicateId(_))
+    ));
+    for reserved in ["epub-id-1", "epub-creator-1", "epub-title-1", "epub-subject-1"] {
+        let options = ResourceOptions::new().id(reserved);
+        assert!(matches!(
+            builder.add_resource_with_options("c.png", b"".as_ref(), "image/png", options),
+            Err(crate::Error::DuplicateId(_))
+        ));
+    }
+    assert!(matches!(
+        builder.add_content(EpubContent::new("c.xhtml", b"".as_ref()).id("1st")),
+        Err(crate::Error::InvalidId(_))
+    ));
+    assert_eq!(builder.manifest_id("a b.xhtml"), Some("id_a_b.xhtml"));
+    assert_eq!(builder.manifest_id("a_b.xhtml"), Some("id_a_b.xhtml_2"));
+    assert_eq!(builder.manifest_id("logo.png"), Some("logo"));
+    assert_eq!(builder.manifest_id("c.xhtml"), None);
+
+    // Ids don't change when other files are removed
+    builder.remove("a b.xhtml").unwrap();
+    assert_eq!(builder.manifest_id("a_b.xhtml"), Some("id_a_b.xhtml_2"));
+
+    let zip = builder.into_zip().unwrap();
+    let opf = zip.get_str("OEBPS/content.opf").unwrap();
+    assert!(opf.contains("id=\"logo\" href=\"logo.png\""));
+    assert!(opf.contains("<itemref idref=\"id_a_b.xhtml_2\"/>"));
+}
+
+#[cfg(feature = "zip-stream")]
+#[test]
+fn wrap_fragments() {
+    let mut builder = EpubBuilder::new(crate::ZipMemory::new()).unwrap();
+    let content = EpubContent::from_fragment("text/chapter.xhtml", b"<p>Text</p>".as_ref());
+    builder
+        .epub_version(EpubVersion::V30)
+        .metadata("lang", "fr")
+        .unwrap()
+        .add_content(content.title("One"))
+        .unwrap();
+
+    let zip = builder.into_zip().unwrap();
+    let page = zip.get_str("OEBPS/text/chapter.xhtml").unwrap();
+    assert!(page.starts_with("<?xml"));
+    assert!(page.contains("xml:lang=\"fr\""));
+    assert!(page.contains("<title>One</title>"));
+    assert!(page.contains("href=\"../stylesheet.css\""));
+    assert!(page.contains("<body>\n<p>Text</p>\n</body>"));
+
+    let mut builder = EpubBuilder::new(crate::ZipMemory::new()).unwrap();
+    assert!(builder.page_template("{{ title").is_err());
+    builder
+        .metadata("title", "Book")
+        .unwrap()
+        .page_template("<p>{{ title }}</p>{{ body }}")
+        .unwrap()
+        .add_content(EpubContent::from_fragment("chapter.xhtml", b"Text".as_ref()))
+        .unwrap();
+    let zip = builder.into_zip().unwrap();
+    assert_eq!(zip.get_str("OEBPS/chapter.xhtml"), Some("<p>Book</p>Text"));
+}
+
+#[cfg(feature = "zip-stream")]
+#[test]
+fn generated_cover_page() {
+    // A 1x2 GIF image
+    let image = b"GIF89a\x01\x00\x02\x00\x00\x00\x00;";
+    let mut builder = EpubBuilder::new(crate::ZipMemory::new()).unwrap();
+    builder
+        .epub_version(EpubVersion::V30)
+        .add_content(EpubContent::new("chapter.xhtml", b"".as_ref()))
+        .unwrap()
+        .add_cover_image("images/cover.gif", image.as_ref(), "image/gif")
+        .unwrap()
+        .cover_page(false);
+
+    let zip = builder.into_zip().unwrap();
+    let page = zip.get_str("OEBPS/cover.xhtml").unwrap();
+    assert!(page.contains("viewBox=\"0 0 1 2\""));
+    assert!(page.contains("xlink:href=\"images/cover.gif\""));
+    let opf = zip.get_str("OEBPS/content.opf").unwrap();
+    let cover = opf.find("<itemref idref=\"id_cover.xhtml\" linear=\"no\"/>").unwrap();
+    assert!(cover < opf.find("<itemref idref=\"id_chapter.xhtml\"/>").unwrap());
+    assert!(opf.contains("properties=\"svg\" id=\"id_cover.xhtml\""));
+    assert!(opf.contains("<reference type=\"cover\" title=\"Cover\" href=\"cover.xhtml\"/>"));
+}
+
+#[cfg(feature = "zip-stream")]
+#[test]
+fn generated_pages() {
+    let mut builder = EpubBuilder::new(crate::ZipMemory::new()).unwrap();
+    builder
+        .metadata("title", "Book")
+        .unwrap()
+        .metadata("author", "Ann")
+        .unwrap()
+        .metadata("author", "Bob")
+        .unwrap()
+        .metadata("publisher", "P & Co")
+        .unwrap()
+        .metadata("license", "CC-BY")
+        .unwrap();
+    builder.set_uuid(uuid::Uuid::nil());
+    builder
+        .add_content(EpubContent::new("one.xhtml", b"".as_ref()))
+        .unwrap()
+        .add_content(EpubContent::new("two.xhtml", b"".as_ref()))
+        .unwrap()
+        .generate_page(GeneratedPage::Colophon, SpinePosition::End)
+        .generate_page(GeneratedPage::TitlePage, SpinePosition::Start)
+        .generate_page(GeneratedPage::Copyright, SpinePosition::Start)
+        .generate_page(GeneratedPage::Colophon, SpinePosition::After("one.xhtml".into()))
+        .generated_page_template(GeneratedPage::Colophon, "<p>{{ author_names }}</p>")
+        .unwrap();
+
+    let zip = builder.into_zip().unwrap();
+    let opf = zip.get_str("OEBPS/content.opf").unwrap();
+    let spine: Vec<_> = opf
+        .match_indices("<itemref idref=\"")
+        .map(|(i, _)| opf[i + 16..].split('"').next().unwrap())
+        .collect();
+    assert_eq!(
+        spine,
+        [
+            "id_title_page.xhtml",
+            "id_copyright.xhtml",
+            "id_one.xhtml",
+            "id_colophon.xhtml",
+            "id_two.xhtml"
+        ]
+    );
+    assert!(opf.contains("<dc:publisher>P &amp; Co</dc:publisher>"));
+    assert!(opf.contains("<reference type=\"copyright\" title=\"Copyright\""));
+    let title_page = zip.get_str("OEBPS/title_page.xhtml").unwrap();
+    assert!(title_page.contains("<h1 class=\"title\">Book</h1>"));
+    assert!(title_page.contains("<p class=\"publisher\">P &amp; Co</p>"));
+    let copyright = zip.get_str("OEBPS/copyright.xhtml").unwrap();
+    assert!(copyright.contains("Ann, Bob"));
+    assert!(copyright.contains("urn:uuid:00000000-0000-0000-0000-000000000000"));
+    let colophon = zip.get_str("OEBPS/colophon.xhtml").unwrap();
+    assert!(colophon.contains("<body>\n<p>Ann, Bob</p>\n</body>"));
+}
+
+#[cfg(feature = "zip-stream")]
+#[test]
+fn footnotes_and_endnotes() {
+    fn build(style: NoteStyle) -> crate::ZipMemory {
+        let mut builder = EpubBuilder::new(crate::ZipMemory::new()).unwrap();
+        builder.epub_version(EpubVersion::V30).note_style(style);
+        let first = builder.add_note("text/one.xhtml", "<p>First</p>").unwrap();
+        let second = builder.add_note("two.xhtml", "<p>Second</p>").unwrap();
+        builder
+            .add_content(EpubContent::from_fragment(
+                "text/one.xhtml",
+                format!("<p>One{first}</p>").as_bytes(),
+            ))
+            .unwrap()
+            .add_content(EpubContent::new(
+                "two.xhtml",
+                format!("<html><body><p>Two{second}</p></body></html>").as_bytes(),
+            ))
+            .unwrap();
+        builder.into_zip().unwrap()
+    }
+
+    let zip = build(NoteStyle::Footnotes);
+    let one = zip.get_str("OEBPS/text/one.xhtml").unwrap();
+    assert!(one.contains("href=\"#note-1\">1</a>"));
+    assert!(one.contains("<aside epub:type=\"footnote\" role=\"doc-footnote\" id=\"note-1\">"));
+    assert!(!one.contains("note-2"));
+    let two = zip.get_str("OEBPS/two.xhtml").unwrap();
+    assert!(two.contains("id=\"note-2\">\n  <a href=\"#noteref-2\""));
+    assert!(two.ends_with("</aside>\n</body></html>"));
+    assert!(zip.get("OEBPS/notes.xhtml").is_none());
+
+    let zip = build(NoteStyle::Endnotes);
+    let one = zip.get_str("OEBPS/text/one.xhtml").unwrap();
+    assert!(one.contains("href=\"../notes.xhtml#note-1\">1</a>"));
+    assert!(!one.contains("<aside"));
+    let notes = zip.get_str("OEBPS/notes.xhtml").unwrap();
+    assert!(notes.contains("<a href=\"text/one.xhtml#noteref-1\" role=\"doc-backlink\">"));
+    assert!(notes.contains("<li epub:type=\"endnote\" role=\"doc-endnote\" id=\"note-2\">"));
+    let opf = zip.get_str("OEBPS/content.opf").unwrap();
+    assert!(opf.contains("<reference type=\"notes\" title=\"Notes\" href=\"notes.xhtml\"/>"));
+}
+
+#[cfg(feature = "zip-stream")]
+#[test]
+fn index_document() {
+    let mut builder = EpubBuilder::new(crate::ZipMemory::new()).unwrap();
+    builder
+        .epub_version(EpubVersion::V30)
+        .add_content(EpubContent::from_fragment(
+            "text/one.xhtml",
+            "<p id=\"cat\">Cat</p>".as_bytes(),
+        ))
+        .unwrap()
+        .add_index_entry(IndexEntry::new("Cat").locator("text/./one.xhtml#cat"))
+        .unwrap();
+    let zip = builder.into_zip().unwrap();
+    let index = zip.get_str("OEBPS/index.xhtml").unwrap();
+    assert!(index.contains("<section epub:type=\"index\">"));
+    assert!(index.contains("<a epub:type=\"index-locator\" href=\"text/one.xhtml#cat\">1</a>"));
+    let opf = zip.get_str("OEBPS/content.opf").unwrap();
+    assert!(opf.contains("<reference type=\"index\" title=\"Index\" href=\"index.xhtml\"/>"));
+    assert!(opf.contains("properties=\"index\" id=\"id_index.xhtml\""));
+    assert!(opf.contains("  <collection role=\"index\">\n    <link href=\"index.xhtml\"/>\n  </collection>\n</package>"));
+}
+
+#[cfg(feature = "zip-stream")]
+#[test]
+fn glossary_and_dictionary() {
+    let mut builder = EpubBuilder::new(crate::ZipMemory::new()).unwrap();
+    builder.epub_version(EpubVersion::V30);
+    let href = builder.add_glossary_entry(GlossaryEntry::new("EPUB", "<p>A format.</p>"));
+    assert_eq!(href, "glossary.xhtml#glossary-1");
+    let href = builder.add_dictionary_entry(GlossaryEntry::new("cat", "<p>Cat</p>").form("cats"));
+    assert_eq!(href, "dictionary.xhtml#dictionary-1");
+    builder.dictionary_target_language("fr");
+    let zip = builder.into_zip().unwrap();
+    let glossary = zip.get_str("OEBPS/glossary.xhtml").unwrap();
+    assert!(glossary.contains("<dt epub:type=\"glossterm\" id=\"glossary-1\"><dfn>EPUB</dfn></dt>"));
+    let map = zip.get_str("OEBPS/dictionary.xml").unwrap();
+    assert!(map.contains("<value value=\"cats\"/>"));
+    let opf = zip.get_str("OEBPS/content.opf").unwrap();
+    assert!(opf.contains("properties=\"glossary\" id=\"id_glossary.xhtml\""));
+    assert!(opf.contains(
+        "<item media-type=\"application/vnd.epub.search-key-map+xml\" \
+         properties=\"search-key-map\" id=\"id_dictionary.xml\" href=\"dictionary.xml\"/>"
+    ));
+    assert!(!opf.contains("<itemref idref=\"id_dictionary.xml\""));
+    assert!(opf.contains("<reference type=\"glossary\" title=\"Dictionary\" href=\"dictionary.xhtml\"/>"));
+    assert!(opf.contains("<collection role=\"glossary\">"));
+    assert!(opf.contains(
+        "<dc:type>dictionary</dc:type>\n      \
+         <meta property=\"source-language\">en</meta>\n      \
+         <meta property=\"target-language\">fr</meta>"
+    ));
+}
+
+#[cfg(feature = "zip-stream")]
+#[test]
+fn bibliography_document() {
+    #[derive(Debug)]
+    struct TitleOnly;
+
+    impl CitationFormatter for TitleOnly {
+        fn format(&self, citation: &Citation, _escape_html: bool) -> String {
+            format!("<cite>{}</cite>", citation.title)
+        }
+    }
+
+    let mut builder = EpubBuilder::new(crate::ZipMemory::new()).unwrap();
+    builder.epub_version(EpubVersion::V30);
+    let href = builder.add_citation(Citation::new("one", "One")).unwrap();
+    assert_eq!(href, "bibliography.xhtml#one");
+    assert!(matches!(
+        builder.add_citation(Citation::new("one", "Two")),
+        Err(crate::Error::DuplicateId(_))
+    ));
+    assert!(matches!(
+        builder.add_citation(Citation::new("1", "Two")),
+        Err(crate::Error::InvalidId(_))
+    ));
+    builder.bibliography_formatter(TitleOnly);
+    let zip = builder.into_zip().unwrap();
+    let bibliography = zip.get_str("OEBPS/bibliography.xhtml").unwrap();
+    assert!(bibliography.contains(
+        "<li epub:type=\"biblioentry\" role=\"doc-biblioentry\" id=\"one\"><cite>One</cite></li>"
+    ));
+    let opf = zip.get_str("OEBPS/content.opf").unwrap();
+    assert!(opf.contains(
+        "<reference type=\"bibliography\" title=\"Bibliography\" href=\"bibliography.xhtml\"/>"
+    ));
+}
+
+#[cfg(feature = "zip-stream")]
+#[test]
+fn localized_labels() {
+    let mut builder = EpubBuilder::new(crate::ZipMemory::new()).unwrap();
+    builder
+        .epub_version(EpubVersion::V30)
+        .inline_toc()
+        .set_label(Label::Notes, "Notes & comments")
+        .note_style(NoteStyle::Endnotes)
+        .generate_page(GeneratedPage::Colophon, SpinePosition::End)
+        .metadata("lang", "fr")
+        .unwrap()
+        .metadata("author", "Ann")
+        .unwrap();
+    builder.add_note("notes.xhtml", "<p>Note</p>").unwrap();
+    let zip = builder.into_zip().unwrap();
+    let nav = zip.get_str("OEBPS/nav.xhtml").unwrap();
+    assert!(nav.contains("<h1 id=\"toc-title\">Table des matières</h1>"));
+    assert!(nav.contains("<h2>Repères</h2>"));
+    assert!(nav.contains("<a href=\"toc.xhtml\">Table des matières</a>"));
+    assert!(nav.contains("<a epub:type=\"endnotes\" href=\"notes.xhtml\">Notes &amp; comments</a>"));
+    let notes = zip.get_str("OEBPS/notes.xhtml").unwrap();
+    assert!(notes.contains("<h1>Notes &amp; comments</h1>"));
+    assert!(notes.contains("<title>Notes &amp; comments</title>"));
+    let colophon = zip.get_str("OEBPS/colophon.xhtml").unwrap();
+    assert!(colophon.contains("<title>Colophon</title>"));
+    assert!(colophon.contains("<p>Par Ann</p>"));
+    assert!(colophon.contains("<p>Généré avec Rust EPUB library"));
+}
+
+#[cfg(feature = "zip-stream")]
+#[test]
+fn language_and_direction() {
+    let mut builder = EpubBuilder::new(crate::ZipMemory::new()).unwrap();
+    builder
+        .epub_version(EpubVersion::V30)
+        .epub_direction(PageDirection::Rtl);
+    builder.set_lang("ar");
+    builder
+        .add_content(
+            EpubContent::new("one.xhtml", b"".as_ref())
+                .title("One")
+                .child(TocElement::new("one.xhtml#en", "English").lang("en")),
+        )
+        .unwrap();
+    let zip = builder.into_zip().unwrap();
+    let opf = zip.get_str("OEBPS/content.opf").unwrap();
+    assert!(opf.contains("page-progression-direction=\"rtl\""));
+    let nav = zip.get_str("OEBPS/nav.xhtml").unwrap();
+    assert!(nav.contains("xml:lang=\"ar\" lang=\"ar\" dir=\"rtl\">"));
+    assert!(nav.contains("<a href=\"one.xhtml#en\" xml:lang=\"en\">English</a>"));
+    let ncx = zip.get_str("OEBPS/toc.ncx").unwrap();
+    assert!(ncx.contains("xml:lang=\"ar\" dir=\"rtl\">"));
+    assert!(ncx.contains("<navLabel xml:lang=\"en\">"));
+}
+
+#[cfg(feature = "zip-stream")]
+#[test]
+fn multilingual_titles() {
+    let mut builder = EpubBuilder::new(crate::ZipMemory::new()).unwrap();
+    builder.epub_version(EpubVersion::V30);
+    builder.set_lang("fr");
+    builder
+        .add_title(Title::new("The Little Prince").lang("en").display_seq(2))
+        .add_title(Title::new("Le Petit Prince").lang("fr").display_seq(1));
+    assert_eq!(builder.main_title(), "Le Petit Prince");
+    let zip = builder.into_zip().unwrap();
+    let opf = zip.get_str("OEBPS/content.opf").unwrap();
+    assert!(opf.contains(
+        "    <dc:title id=\"epub-title-1\" xml:lang=\"fr\">Le Petit Prince</dc:title>
+    <meta refines=\"#epub-title-1\" property=\"display-seq\">1</meta>
+    <dc:title id=\"epub-title-2\" xml:lang=\"en\">The Little Prince</dc:title>
+    <meta refines=\"#epub-title-2\" property=\"display-seq\">2</meta>
+"
+    ));
+    let ncx = zip.get_str("OEBPS/toc.ncx").unwrap();
+    assert!(ncx.contains("<docTitle>\n    <text>Le Petit Prince</text>"));
+    let nav = zip.get_str("OEBPS/nav.xhtml").unwrap();
+    assert!(nav.contains("<title>Le Petit Prince</title>"));
+}
+
+#[cfg(feature = "zip-stream")]
+#[test]
+fn subtitle_and_edition() {
+    fn build(version: EpubVersion) -> String {
+        let mut builder = EpubBuilder::new(crate::ZipMemory::new()).unwrap();
+        builder
+            .epub_version(version)
+            .metadata("title", "Book")
+            .unwrap()
+            .metadata("subtitle", "A subtitle")
+            .unwrap()
+            .metadata("edition", "Second edition")
+            .unwrap();
+        builder.set_subtitle("The subtitle");
+        let zip = builder.into_zip().unwrap();
+        zip.get_str("OEBPS/content.opf").unwrap().to_string()
+    }
+
+    let opf = build(EpubVersion::V20);
+    assert!(opf.contains("<dc:title>Book: The subtitle (Second edition)</dc:title>"));
+    let opf = build(EpubVersion::V30);
+    assert!(opf.contains("<meta refines=\"#epub-title-1\" property=\"title-type\">main</meta>"));
+    assert!(opf.contains("<dc:title id=\"epub-title-2\">Second edition</dc:title>"));
+    assert!(opf.contains("<dc:title id=\"epub-title-3\">The subtitle</dc:title>"));
+    assert!(!opf.contains("A subtitle"));
+}
+
+#[cfg(feature = "zip-stream")]
+#[test]
+fn structured_subjects() {
+    // Codes are checked when the EPUB is generated, whenever validation was enabled
+    let mut builder = EpubBuilder::new(crate::ZipMemory::new()).unwrap();
+    builder
+        .add_structured_subject(Subject::new("Fiction").code(crate::SubjectAuthority::Thema, "fb"))
+        .validate_subject_codes(true);
+    assert!(matches!(
+        builder.into_zip(),
+        Err(crate::Error::InvalidSubjectCode { .. })
+    ));
+
+    let mut builder = EpubBuilder::new(crate::ZipMemory::new()).unwrap();
+    builder.epub_version(EpubVersion::V30);
+    builder.add_subject("Free text");
+    builder.add_description_with_lang("Un roman", "fr");
+    builder.add_structured_subject(Subject::new("Fiction").code(crate::SubjectAuthority::Bisac, "FIC000000"));
+    let zip = builder.into_zip().unwrap();
+    let opf = zip.get_str("OEBPS/content.opf").unwrap();
+    assert!(opf.contains("<dc:description xml:lang=\"fr\">Un roman</dc:description>"));
+    assert!(opf.contains(
+        "    <dc:subject>Free text</dc:subject>
+    <dc:subject id=\"epub-subject-1\">Fiction</dc:subject>
+    <meta refines=\"#epub-subject-1\" property=\"authority\">BISAC</meta>
+    <meta refines=\"#epub-subject-1\" property=\"term\">FIC000000</meta>
+"
+    ));
+}
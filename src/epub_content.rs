@@ -4,8 +4,6 @@
 
 use crate::TocElement;
 
-use std::io::Read;
-
 /// Represents the possible reference type of an EPUB page.
 ///
 /// Used by the guide section of EPUB 2.0 and the lankmarks navigation section
@@ -72,16 +70,26 @@ pub enum ReferenceType {
 ///     .child(TocElement::new("intro.xhtml#2", "Section 2"));
 /// ```
 #[derive(Debug)]
-pub struct EpubContent<R: Read> {
+pub struct EpubContent<R> {
     /// The title and url, plus sublevels
     pub toc: TocElement,
-    /// The content
+    /// The content, usually something implementing `Read`
     pub content: R,
     /// Properties. See [EpubProperties](enum.EpubProperties.html)
     pub reftype: Option<ReferenceType>,
+    /// Id of this content in the manifest; if `None`, it is derived from the href
+    pub id: Option<String>,
+    /// Whether `content` is only the body of the page, which the builder then wraps in a
+    /// complete XHTML document when the EPUB is generated
+    pub fragment: bool,
+    /// Whether `content` contains scripts, which is declared in the manifest in EPUB 3
+    pub scripted: bool,
+    /// Whether the builder writes the XML declaration and the doctype of the EPUB version
+    /// before `content`
+    pub(crate) xml_prolog: bool,
 }
 
-impl<R: Read> EpubContent<R> {
+impl<R> EpubContent<R> {
     /// Creates a new EpubContent
     ///
     /// By default, this element is at level 1, and it has no title
@@ -91,9 +99,36 @@ impl<R: Read> EpubContent<R> {
             content,
             toc: TocElement::new(href, ""),
             reftype: None,
+            id: None,
+            fragment: false,
+            scripted: false,
+            xml_prolog: false,
         }
     }
 
+    /// Creates a new EpubContent from the body of a page
+    ///
+    /// Unlike `new`, `content` is only a fragment, e.g. `<h1>Chapter 1</h1><p>Some text</p>`,
+    /// and the builder wraps it in a complete XHTML document, using the language, direction
+    /// and stylesheet of the book, and the title of this content. The template used to do
+    /// so can be changed with the `page_template` method of
+    /// [`EpubBuilder`](struct.EpubBuilder.html).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use epub_builder::EpubContent;
+    ///
+    /// let body = "<h1>Chapter 1</h1><p>Some text</p>";
+    /// let content = EpubContent::from_fragment("chapter_1.xhtml", body.as_bytes())
+    ///     .title("Chapter 1");
+    /// ```
+    pub fn from_fragment<S: Into<String>>(href: S, content: R) -> Self {
+        let mut content = EpubContent::new(href, content);
+        content.fragment = true;
+        content
+    }
+
     /// Set the title of this content. If no title is set,
     /// this part of the book will not be displayed in the table of content.
     pub fn title<S: Into<String>>(mut self, title: S) -> Self {
@@ -145,4 +180,14 @@ impl<R: Read> EpubContent<R> {
         self.reftype = Some(reftype);
         self
     }
+
+    /// Sets the id of this content in the manifest, instead of deriving it from the href
+    ///
+    /// This is useful to reference it, e.g. from metadata refinements. The id must be a
+    /// valid XML id, and be unique in the EPUB. Ids starting with `epub-` are reserved for
+    /// metadata.
+    pub fn id<S: Into<String>>(mut self, id: S) -> Self {
+        self.id = Some(id.into());
+        self
+    }
 }
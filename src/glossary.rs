@@ -0,0 +1,226 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with
+// this file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use crate::common;
+use crate::EpubVersion;
+
+/// Path of the generated glossary document
+pub(crate) const GLOSSARY_FILE: &str = "glossary.xhtml";
+/// Path of the generated dictionary document
+pub(crate) const DICTIONARY_FILE: &str = "dictionary.xhtml";
+/// Path of the search key map of the dictionary
+pub(crate) const SEARCH_KEY_MAP_FILE: &str = "dictionary.xml";
+
+/// An entry of the glossary or dictionary generated by
+/// [`EpubBuilder`](struct.EpubBuilder.html).
+///
+/// The definition is XHTML, e.g. `<p>A small domesticated feline.</p>`, and is not escaped.
+///
+/// # Example
+///
+/// ```
+/// use epub_builder::GlossaryEntry;
+///
+/// let entry = GlossaryEntry::new("cat", "<p>A small domesticated feline.</p>")
+///     .form("cats");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GlossaryEntry {
+    /// The term
+    pub term: String,
+    /// XHTML definition of the term
+    pub definition: String,
+    /// Other forms of the term (e.g. plural or inflections), which reading systems can
+    /// look up in dictionaries
+    pub forms: Vec<String>,
+}
+
+impl GlossaryEntry {
+    /// Creates a new entry
+    pub fn new<S1, S2>(term: S1, definition: S2) -> Self
+    where
+        S1: Into<String>,
+        S2: Into<String>,
+    {
+        GlossaryEntry {
+            term: term.into(),
+            definition: definition.into(),
+            forms: vec![],
+        }
+    }
+
+    /// Adds another form of the term
+    pub fn form<S: Into<String>>(mut self, form: S) -> Self {
+        self.forms.push(form.into());
+        self
+    }
+}
+
+/// Returns the entries sorted alphabetically, with their number (starting at 1) in
+/// order of addition, used for their ids
+fn sorted(entries: &[GlossaryEntry]) -> Vec<(usize, &GlossaryEntry)> {
+    let mut sorted: Vec<(usize, &GlossaryEntry)> = entries
+        .iter()
+        .enumerate()
+        .map(|(i, entry)| (i + 1, entry))
+        .collect();
+    sorted.sort_by_cached_key(|(_, entry)| entry.term.to_lowercase());
+    sorted
+}
+
+/// Renders the body of the glossary document, with `title`, which must already be escaped
+pub(crate) fn render_glossary(
+    version: EpubVersion,
+    title: &str,
+    entries: &[GlossaryEntry],
+    escape_html: bool,
+) -> String {
+    let (element, semantics, term_semantics, definition_semantics) = match version {
+        EpubVersion::V20 => (
+            "div",
+            "class=\"glossary\"",
+            "class=\"glossterm\"",
+            "class=\"glossdef\"",
+        ),
+        EpubVersion::V30 => (
+            "section",
+            "epub:type=\"glossary\" role=\"doc-glossary\"",
+            "epub:type=\"glossterm\"",
+            "epub:type=\"glossdef\"",
+        ),
+    };
+    let items: Vec<String> = sorted(entries)
+        .into_iter()
+        .map(|(number, entry)| {
+            format!(
+                "<dt {term_semantics} id=\"glossary-{number}\"><dfn>{term}</dfn></dt>\n\
+                 <dd {definition_semantics}>\n{definition}\n</dd>",
+                term = common::encode_html(&entry.term, escape_html),
+                definition = common::indent(&entry.definition, 1), // Not escaped: XML content
+            )
+        })
+        .collect();
+    format!(
+        "<{element} {semantics}>\n  <h1>{title}</h1>\n  <dl>\n{items}\n  </dl>\n</{element}>",
+        items = common::indent(items.join("\n"), 2), // Not escaped: XML content
+    )
+}
+
+/// Renders the body of the dictionary document, with `title`, which must already be escaped
+pub(crate) fn render_dictionary(
+    version: EpubVersion,
+    title: &str,
+    entries: &[GlossaryEntry],
+    escape_html: bool,
+) -> String {
+    let (element, semantics, entry_element, entry_semantics) = match version {
+        EpubVersion::V20 => ("div", "class=\"dictionary\"", "div", "class=\"dictentry\""),
+        EpubVersion::V30 => (
+            "section",
+            "epub:type=\"dictionary\"",
+            "article",
+            "epub:type=\"dictentry\"",
+        ),
+    };
+    let items: Vec<String> = sorted(entries)
+        .into_iter()
+        .map(|(number, entry)| {
+            format!(
+                "<{entry_element} {entry_semantics} id=\"dictionary-{number}\">\n  \
+                 <h2><dfn>{term}</dfn></h2>\n{definition}\n</{entry_element}>",
+                term = common::encode_html(&entry.term, escape_html),
+                definition = common::indent(&entry.definition, 1), // Not escaped: XML content
+            )
+        })
+        .collect();
+    format!(
+        "<{element} {semantics}>\n  <h1>{title}</h1>\n{items}\n</{element}>",
+        items = common::indent(items.join("\n"), 1), // Not escaped: XML content
+    )
+}
+
+/// Renders the search key map of the dictionary, listing the forms of each term
+pub(crate) fn render_search_key_map(lang: &str, entries: &[GlossaryEntry]) -> String {
+    let groups: Vec<String> = entries
+        .iter()
+        .enumerate()
+        .map(|(i, entry)| {
+            let values: Vec<String> = entry
+                .forms
+                .iter()
+                .map(|form| {
+                    format!(
+                        "\n    <value value=\"{}\"/>",
+                        html_escape::encode_double_quoted_attribute(form)
+                    )
+                })
+                .collect();
+            format!(
+                "<search-key-group href=\"{file}#dictionary-{number}\">\n  \
+                 <match value=\"{term}\">{values}\n  </match>\n</search-key-group>",
+                file = DICTIONARY_FILE,
+                number = i + 1,
+                term = html_escape::encode_double_quoted_attribute(&entry.term),
+                values = values.concat(), // Not escaped: XML content
+            )
+        })
+        .collect();
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <search-key-map xmlns=\"http://www.idpf.org/2007/ops\" xml:lang=\"{lang}\">\n\
+         {groups}\n</search-key-map>\n",
+        lang = html_escape::encode_double_quoted_attribute(lang),
+        groups = common::indent(groups.join("\n"), 1), // Not escaped: XML content
+    )
+}
+
+#[test]
+fn glossary_markup() {
+    let entries = vec![
+        GlossaryEntry::new("dog", "<p>Dog</p>"),
+        GlossaryEntry::new("Cat", "<p>Cat</p>").form("cats"),
+    ];
+    assert_eq!(
+        render_glossary(EpubVersion::V30, "Glossary", &entries, true),
+        "<section epub:type=\"glossary\" role=\"doc-glossary\">
+  <h1>Glossary</h1>
+  <dl>
+    <dt epub:type=\"glossterm\" id=\"glossary-2\"><dfn>Cat</dfn></dt>
+    <dd epub:type=\"glossdef\">
+      <p>Cat</p>
+    </dd>
+    <dt epub:type=\"glossterm\" id=\"glossary-1\"><dfn>dog</dfn></dt>
+    <dd epub:type=\"glossdef\">
+      <p>Dog</p>
+    </dd>
+  </dl>
+</section>"
+    );
+    assert_eq!(
+        render_dictionary(EpubVersion::V20, "Dictionary", &entries[1..], true),
+        "<div class=\"dictionary\">
+  <h1>Dictionary</h1>
+  <div class=\"dictentry\" id=\"dictionary-1\">
+    <h2><dfn>Cat</dfn></h2>
+    <p>Cat</p>
+  </div>
+</div>"
+    );
+    assert_eq!(
+        render_search_key_map("en", &entries),
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<search-key-map xmlns=\"http://www.idpf.org/2007/ops\" xml:lang=\"en\">
+  <search-key-group href=\"dictionary.xhtml#dictionary-1\">
+    <match value=\"dog\">
+    </match>
+  </search-key-group>
+  <search-key-group href=\"dictionary.xhtml#dictionary-2\">
+    <match value=\"Cat\">
+      <value value=\"cats\"/>
+    </match>
+  </search-key-group>
+</search-key-map>
+"
+    );
+}
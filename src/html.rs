@@ -0,0 +1,340 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with
+// this file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use crate::ContentSource;
+use crate::EpubContent;
+
+use scraper::node::Element;
+use scraper::{Html, Node};
+
+const XHTML_NS: &str = "http://www.w3.org/1999/xhtml";
+const SVG_NS: &str = "http://www.w3.org/2000/svg";
+const XLINK_NS: &str = "http://www.w3.org/1999/xlink";
+const XML_NS: &str = "http://www.w3.org/XML/1998/namespace";
+const EPUB_NS: &str = "http://www.idpf.org/2007/ops";
+
+/// HTML elements that can't have content, and are written as `<br />`
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
+    "wbr",
+];
+
+/// Options to convert HTML to XHTML with `EpubContent::from_html_with_options`.
+///
+/// # Example
+///
+/// ```
+/// use epub_builder::HtmlOptions;
+///
+/// // Only report scripts, without removing them
+/// let options = HtmlOptions::new().strip_scripts(false);
+/// ```
+#[non_exhaustive]
+#[derive(Debug, Clone)]
+pub struct HtmlOptions {
+    /// Whether scripts (`<script>` elements, `on*` event handler attributes and
+    /// `javascript:` links) are removed (default: true)
+    pub strip_scripts: bool,
+}
+
+impl Default for HtmlOptions {
+    fn default() -> Self {
+        HtmlOptions {
+            strip_scripts: true,
+        }
+    }
+}
+
+impl HtmlOptions {
+    /// Creates default options
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets whether scripts are removed, or only reported
+    pub fn strip_scripts(mut self, strip: bool) -> Self {
+        self.strip_scripts = strip;
+        self
+    }
+}
+
+/// What was found (and possibly removed) while converting HTML to XHTML.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HtmlReport {
+    /// Scripts found in the content, e.g. `script` or `a@onclick`
+    pub scripts: Vec<String>,
+    /// Attributes that were dropped because their names are not valid in XML
+    pub invalid_attributes: Vec<String>,
+}
+
+impl EpubContent<ContentSource> {
+    /// Creates a content from HTML, which doesn't have to be well-formed.
+    ///
+    /// The HTML is parsed like a browser would, then written as XHTML. If it is a
+    /// complete document (with a doctype, a head, or attributes on its `<html>` or
+    /// `<body>` element) it is kept as such, and the builder adds the doctype of the EPUB
+    /// version; else it is considered as a body fragment, and the builder wraps it in a
+    /// page with the language and stylesheet of the book.
+    ///
+    /// Scripts are removed, since they are not allowed in content that isn't declared
+    /// as scripted; the returned report lists what was removed. If they are kept with
+    /// `HtmlOptions::strip_scripts`, the content is declared as scripted.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use epub_builder::EpubContent;
+    ///
+    /// let html = "<p>Some <b>text<p>Another paragraph<br><script>alert(1)</script>";
+    /// let (content, report) = EpubContent::from_html("chapter.xhtml", html);
+    /// assert!(content.fragment);
+    /// assert_eq!(report.scripts, ["script"]);
+    /// ```
+    pub fn from_html<S: Into<String>>(href: S, html: &str) -> (Self, HtmlReport) {
+        Self::from_html_with_options(href, html, HtmlOptions::default())
+    }
+
+    /// Creates a content from HTML, with some options
+    ///
+    /// This works like `from_html`, except that `options` allows to customize the
+    /// conversion.
+    pub fn from_html_with_options<S: Into<String>>(
+        href: S,
+        html: &str,
+        options: HtmlOptions,
+    ) -> (Self, HtmlReport) {
+        let document = Html::parse_document(html);
+        let html_element = document.root_element();
+        let mut writer = XhtmlWriter {
+            options,
+            report: HtmlReport::default(),
+            output: String::new(),
+        };
+        let is_document = is_complete_document(&document);
+        if is_document {
+            writer.write_children(document.tree.root(), None);
+        } else {
+            // Elements such as `<script>` or `<style>` at the beginning of the fragment
+            // are put in the head by the parser
+            for child in html_element.children() {
+                writer.write_children(child, Some(XHTML_NS));
+            }
+        }
+
+        let scripted = !writer.options.strip_scripts && !writer.report.scripts.is_empty();
+        let mut content = EpubContent::new(href, ContentSource::bytes(writer.output));
+        content.fragment = !is_document;
+        content.xml_prolog = is_document;
+        content.scripted = scripted;
+        (content, writer.report)
+    }
+}
+
+/// Tells whether parsed HTML is a complete document, rather than the content of a body.
+///
+/// The parser always creates the `<html>`, `<head>` and `<body>` elements, so it is a
+/// document if it has a doctype, a title, or attributes on these elements.
+fn is_complete_document(document: &Html) -> bool {
+    let has_doctype = document
+        .tree
+        .root()
+        .children()
+        .any(|node| node.value().is_doctype());
+    let html = document.root_element();
+    has_doctype
+        || html.value().attrs().next().is_some()
+        || html.children().any(|node| match node.value().as_element() {
+            Some(element) if element.attrs().next().is_some() => true,
+            Some(element) if element.name() == "head" => node
+                .children()
+                .any(|child| child.value().as_element().map(|e| e.name()) == Some("title")),
+            _ => false,
+        })
+}
+
+/// Writes a parsed HTML tree as XHTML
+struct XhtmlWriter {
+    options: HtmlOptions,
+    report: HtmlReport,
+    output: String,
+}
+
+impl XhtmlWriter {
+    /// Writes the children of `node`; `ns` is the namespace of its element, if any
+    fn write_children(&mut self, node: ego_tree::NodeRef<Node>, ns: Option<&str>) {
+        for child in node.children() {
+            match child.value() {
+                Node::Element(element) => self.write_element(child, element, ns),
+                Node::Text(text) => self
+                    .output
+                    .push_str(&html_escape::encode_text_minimal(&text.text)),
+                Node::Comment(comment) if !comment.comment.contains("--") => {
+                    self.output
+                        .push_str(&format!("<!--{}-->", &*comment.comment));
+                }
+                // The doctype is written by the builder, and comments containing `--`
+                // are not valid in XML
+                _ => (),
+            }
+        }
+    }
+
+    /// Writes an element, declaring its namespace if it differs from its parent's
+    fn write_element(
+        &mut self,
+        node: ego_tree::NodeRef<Node>,
+        element: &Element,
+        parent_ns: Option<&str>,
+    ) {
+        let name = &*element.name.local;
+        let ns = &*element.name.ns;
+        if ns == XHTML_NS && name == "script" {
+            self.report.scripts.push(name.to_string());
+            if self.options.strip_scripts {
+                return;
+            }
+        }
+
+        self.output.push('<');
+        self.output.push_str(name);
+        if parent_ns != Some(ns) {
+            self.output.push_str(&format!(" xmlns=\"{ns}\""));
+            if parent_ns.is_none() && ns == XHTML_NS {
+                self.output.push_str(&format!(" xmlns:epub=\"{EPUB_NS}\""));
+            }
+            if ns == SVG_NS {
+                self.output
+                    .push_str(&format!(" xmlns:xlink=\"{XLINK_NS}\""));
+            }
+        }
+        for (attr, value) in &element.attrs {
+            let attr_name = match &*attr.ns {
+                XLINK_NS => format!("xlink:{}", &*attr.local),
+                XML_NS => format!("xml:{}", &*attr.local),
+                // Namespace declarations are written above
+                "http://www.w3.org/2000/xmlns/" => continue,
+                _ => attr.local.to_string(),
+            };
+            if attr_name == "xmlns" || attr_name.starts_with("xmlns:") {
+                continue;
+            }
+            if !is_xml_name(&attr_name) {
+                self.report
+                    .invalid_attributes
+                    .push(format!("{name}@{attr_name}"));
+                continue;
+            }
+            let is_script = attr_name.starts_with("on")
+                || value.trim_start().to_lowercase().starts_with("javascript:");
+            if is_script {
+                self.report.scripts.push(format!("{name}@{attr_name}"));
+                if self.options.strip_scripts {
+                    continue;
+                }
+            }
+            self.output.push_str(&format!(
+                " {attr_name}=\"{}\"",
+                html_escape::encode_double_quoted_attribute(value)
+            ));
+        }
+
+        let is_void = ns == XHTML_NS && VOID_ELEMENTS.contains(&name);
+        if is_void || (ns != XHTML_NS && !node.has_children()) {
+            self.output.push_str(" />");
+        } else {
+            self.output.push('>');
+            self.write_children(node, Some(ns));
+            self.output.push_str(&format!("</{name}>"));
+        }
+    }
+}
+
+/// Tells whether a name can be used for an attribute in XML
+fn is_xml_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c.is_alphabetic() || c == '_' || c == ':' => {
+            chars.all(|c| c.is_alphanumeric() || c == '_' || c == ':' || c == '-' || c == '.')
+        }
+        _ => false,
+    }
+}
+
+#[test]
+fn html_fragment() {
+    let html = "<p class=x>Some <b>text<p>A & B<br><img src=a.png alt='<'>\
+                <a href=\"javascript:go()\" onclick=\"go()\">link</a><svg><circle r=1></svg>";
+    let (content, report) = EpubContent::from_html("chapter.xhtml", html);
+    assert!(content.fragment);
+    assert!(!content.scripted);
+    assert_eq!(report.scripts, ["a@href", "a@onclick"]);
+    let xhtml = String::from_utf8(content.content.read_to_vec().unwrap()).unwrap();
+    assert_eq!(
+        xhtml,
+        "<p class=\"x\">Some <b>text</b></p><p><b>A &amp; B<br /><img alt=\"&lt;\" src=\"a.png\" />\
+         <a>link</a><svg xmlns=\"http://www.w3.org/2000/svg\" \
+         xmlns:xlink=\"http://www.w3.org/1999/xlink\"><circle r=\"1\" /></svg></b></p>"
+    );
+}
+
+#[test]
+fn html_document() {
+    let html = "<!doctype html><html lang=fr><title>T</title><script>x()</script><body>Text";
+    let (content, report) = EpubContent::from_html_with_options(
+        "chapter.xhtml",
+        html,
+        HtmlOptions::new().strip_scripts(false),
+    );
+    assert!(!content.fragment);
+    assert!(content.scripted);
+    assert_eq!(report.scripts, ["script"]);
+    let xhtml = String::from_utf8(content.content.read_to_vec().unwrap()).unwrap();
+    assert_eq!(
+        xhtml,
+        "<html xmlns=\"http://www.w3.org/1999/xhtml\" xmlns:epub=\"http://www.idpf.org/2007/ops\" \
+         lang=\"fr\"><head><title>T</title><script>x()</script></head><body>Text</body></html>"
+    );
+}
+
+#[test]
+fn html_document_detection() {
+    for html in [
+        "<!-- <body> --><p>&lt;html&gt;",
+        "<body><p>Text",
+        "<script>x()</script>",
+    ] {
+        let (content, _) = EpubContent::from_html("chapter.xhtml", html);
+        assert!(content.fragment, "{html}");
+    }
+    for html in [
+        "<!doctype html><p>Text",
+        "<body class=x>Text",
+        "<title>T</title>Text",
+    ] {
+        let (content, _) = EpubContent::from_html("chapter.xhtml", html);
+        assert!(!content.fragment, "{html}");
+    }
+}
+
+#[cfg(feature = "zip-stream")]
+#[test]
+fn html_document_in_book() {
+    let html = "<html lang=fr><body onload=\"go()\">Text</body></html>";
+    let options = HtmlOptions::new().strip_scripts(false);
+    let mut builder = crate::EpubBuilder::new(crate::ZipMemory::new()).unwrap();
+    builder.epub_version(crate::EpubVersion::V30);
+    builder
+        .add_content(EpubContent::from_html_with_options("chapter.xhtml", html, options).0)
+        .unwrap();
+    let zip = builder.into_zip().unwrap();
+    let chapter = zip.get_str("OEBPS/chapter.xhtml").unwrap();
+    assert!(
+        chapter.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<!DOCTYPE html>\n<html")
+    );
+    let opf = zip.get_str("OEBPS/content.opf").unwrap();
+    assert!(
+        opf.contains("properties=\"scripted\" id=\"id_chapter.xhtml\""),
+        "{opf}"
+    );
+}
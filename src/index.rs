@@ -0,0 +1,259 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with
+// this file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use crate::common;
+use crate::EpubVersion;
+use crate::Result;
+
+/// Path of the generated index document
+pub(crate) const INDEX_FILE: &str = "index.xhtml";
+
+/// An entry of the index generated by [`EpubBuilder`](struct.EpubBuilder.html).
+///
+/// An entry has a term, and usually locators, i.e. links to the places where the term
+/// appears. It can also have sub-entries, and references to other entries ("see" and
+/// "see also").
+///
+/// # Example
+///
+/// ```
+/// use epub_builder::IndexEntry;
+///
+/// let entry = IndexEntry::new("Cat")
+///     .locator("chapter_1.xhtml#cat")
+///     .subentry(IndexEntry::new("Siamese").locator("chapter_2.xhtml#siamese"))
+///     .see_also("Dog");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexEntry {
+    /// The term
+    pub term: String,
+    /// Links to where the term appears, with their labels (if `None`, locators are
+    /// numbered)
+    pub locators: Vec<(String, Option<String>)>,
+    /// Sub-entries
+    pub subentries: Vec<IndexEntry>,
+    /// Terms this one refers to, as the preferred term ("see")
+    pub see: Vec<String>,
+    /// Related terms ("see also")
+    pub see_also: Vec<String>,
+}
+
+impl IndexEntry {
+    /// Creates a new entry, with no locator
+    pub fn new<S: Into<String>>(term: S) -> Self {
+        IndexEntry {
+            term: term.into(),
+            locators: vec![],
+            subentries: vec![],
+            see: vec![],
+            see_also: vec![],
+        }
+    }
+
+    /// Adds a link to where the term appears, e.g. `chapter_1.xhtml#cat`
+    pub fn locator<S: Into<String>>(mut self, href: S) -> Self {
+        self.locators.push((href.into(), None));
+        self
+    }
+
+    /// Adds a link to where the term appears, with the text of the link
+    pub fn locator_with_label<S1, S2>(mut self, href: S1, label: S2) -> Self
+    where
+        S1: Into<String>,
+        S2: Into<String>,
+    {
+        self.locators.push((href.into(), Some(label.into())));
+        self
+    }
+
+    /// Adds a sub-entry
+    pub fn subentry(mut self, entry: IndexEntry) -> Self {
+        self.subentries.push(entry);
+        self
+    }
+
+    /// Refers to the preferred term for this one
+    pub fn see<S: Into<String>>(mut self, term: S) -> Self {
+        self.see.push(term.into());
+        self
+    }
+
+    /// Refers to a related term
+    pub fn see_also<S: Into<String>>(mut self, term: S) -> Self {
+        self.see_also.push(term.into());
+        self
+    }
+
+    /// Adds the locators, sub-entries and references of `other`, which has the same term
+    pub(crate) fn merge(&mut self, other: IndexEntry) {
+        self.locators.extend(other.locators);
+        for subentry in other.subentries {
+            add_entry(&mut self.subentries, subentry);
+        }
+        self.see.extend(other.see);
+        self.see_also.extend(other.see_also);
+    }
+
+    /// Normalizes the locators, recursively
+    pub(crate) fn normalize(&mut self) -> Result<()> {
+        for (href, _) in &mut self.locators {
+            *href = common::normalize_url(href)?;
+        }
+        for subentry in &mut self.subentries {
+            subentry.normalize()?;
+        }
+        Ok(())
+    }
+}
+
+/// Adds an entry to a list, merging it with an existing entry for the same term
+pub(crate) fn add_entry(entries: &mut Vec<IndexEntry>, entry: IndexEntry) {
+    match entries.iter_mut().find(|other| other.term == entry.term) {
+        Some(existing) => existing.merge(entry),
+        None => entries.push(entry),
+    }
+}
+
+/// Renders the body of the index document, with `title`, which must already be escaped
+pub(crate) fn render_index(
+    version: EpubVersion,
+    title: &str,
+    entries: &[IndexEntry],
+    escape_html: bool,
+) -> String {
+    let renderer = IndexRenderer {
+        version,
+        entries,
+        escape_html,
+    };
+    let (element, semantics) = match version {
+        EpubVersion::V20 => ("div", "class=\"index\""),
+        EpubVersion::V30 => ("section", "epub:type=\"index\""),
+    };
+    format!(
+        "<{element} {semantics}>\n  <h1>{title}</h1>\n{list}\n</{element}>",
+        list = common::indent(renderer.render_list(entries, "index"), 1), // Not escaped: XML content
+    )
+}
+
+struct IndexRenderer<'a> {
+    version: EpubVersion,
+    /// Top-level entries, to link "see" references
+    entries: &'a [IndexEntry],
+    escape_html: bool,
+}
+
+impl IndexRenderer<'_> {
+    /// Returns the semantics of an element, as `epub:type` or class depending on the version
+    fn semantics(&self, name: &str) -> String {
+        match self.version {
+            EpubVersion::V20 => format!("class=\"{name}\""),
+            EpubVersion::V30 => format!("epub:type=\"{name}\""),
+        }
+    }
+
+    /// Renders a list of entries, sorted alphabetically; `id` prefixes the ids of entries
+    fn render_list(&self, entries: &[IndexEntry], id: &str) -> String {
+        let mut sorted: Vec<(usize, &IndexEntry)> = entries.iter().enumerate().collect();
+        sorted.sort_by_cached_key(|(_, entry)| entry.term.to_lowercase());
+        let items: Vec<String> = sorted
+            .into_iter()
+            .map(|(i, entry)| self.render_entry(entry, &format!("{id}-{}", i + 1)))
+            .collect();
+        format!(
+            "<ul {semantics}>\n{items}\n</ul>",
+            semantics = self.semantics("index-entry-list"),
+            items = common::indent(items.join("\n"), 1), // Not escaped: XML content
+        )
+    }
+
+    fn render_entry(&self, entry: &IndexEntry, id: &str) -> String {
+        let mut line = format!(
+            "<span {semantics}>{term}</span>",
+            semantics = self.semantics("index-term"),
+            term = common::encode_html(&entry.term, self.escape_html),
+        );
+        for (i, (href, label)) in entry.locators.iter().enumerate() {
+            line.push_str(&format!(
+                ", <a {semantics} href=\"{href}\">{label}</a>",
+                semantics = self.semantics("index-locator"),
+                href = html_escape::encode_double_quoted_attribute(&common::encode_url(href)),
+                label = match label {
+                    Some(label) => common::encode_html(label, self.escape_html).into_owned(),
+                    None => (i + 1).to_string(),
+                },
+            ));
+        }
+        for (terms, semantics, label) in [
+            (&entry.see, "index-xref-preferred", "see"),
+            (&entry.see_also, "index-xref-related", "see also"),
+        ] {
+            if !terms.is_empty() {
+                let links: Vec<String> = terms.iter().map(|term| self.render_xref(term)).collect();
+                line.push_str(&format!(
+                    " <span {semantics}>({label} {links})</span>",
+                    semantics = self.semantics(semantics),
+                    links = links.join(", "),
+                ));
+            }
+        }
+        if !entry.subentries.is_empty() {
+            line.push('\n');
+            line.push_str(&self.render_list(&entry.subentries, id));
+        }
+        format!(
+            "<li {semantics} id=\"{id}\">{line}</li>",
+            semantics = self.semantics("index-entry"),
+        )
+    }
+
+    /// Renders a reference to another top-level term, linked if it exists
+    fn render_xref(&self, term: &str) -> String {
+        let text = common::encode_html(term, self.escape_html);
+        match self.entries.iter().position(|entry| entry.term == term) {
+            Some(i) => format!("<a href=\"#index-{}\">{text}</a>", i + 1),
+            None => text.into_owned(),
+        }
+    }
+}
+
+#[test]
+fn index_markup() {
+    let mut entries = vec![];
+    add_entry(
+        &mut entries,
+        IndexEntry::new("dog")
+            .locator("b.xhtml#dog")
+            .see_also("Cat"),
+    );
+    add_entry(&mut entries, IndexEntry::new("Cat").locator("a.xhtml#cat"));
+    add_entry(
+        &mut entries,
+        IndexEntry::new("Cat")
+            .locator_with_label("b.xhtml#cat", "Chapter 2")
+            .subentry(IndexEntry::new("Siamese").locator("b.xhtml#siamese")),
+    );
+    assert_eq!(entries.len(), 2);
+
+    let index = render_index(EpubVersion::V30, "Index", &entries, true);
+    assert_eq!(
+        index,
+        "<section epub:type=\"index\">
+  <h1>Index</h1>
+  <ul epub:type=\"index-entry-list\">
+    <li epub:type=\"index-entry\" id=\"index-2\"><span epub:type=\"index-term\">Cat</span>, \
+<a epub:type=\"index-locator\" href=\"a.xhtml#cat\">1</a>, \
+<a epub:type=\"index-locator\" href=\"b.xhtml#cat\">Chapter 2</a>
+    <ul epub:type=\"index-entry-list\">
+      <li epub:type=\"index-entry\" id=\"index-2-1\"><span epub:type=\"index-term\">Siamese</span>, \
+<a epub:type=\"index-locator\" href=\"b.xhtml#siamese\">1</a></li>
+    </ul></li>
+    <li epub:type=\"index-entry\" id=\"index-1\"><span epub:type=\"index-term\">dog</span>, \
+<a epub:type=\"index-locator\" href=\"b.xhtml#dog\">1</a> \
+<span epub:type=\"index-xref-related\">(see also <a href=\"#index-2\">Cat</a>)</span></li>
+  </ul>
+</section>"
+    );
+}
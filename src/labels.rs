@@ -0,0 +1,157 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with
+// this file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/// A string written by [`EpubBuilder`](struct.EpubBuilder.html) in the documents it
+/// generates.
+///
+/// By default, labels are translated according to the language of the book (the `lang`
+/// metadata), falling back to English for unsupported languages. Supported languages are
+/// English, French, German, Italian, Portuguese and Spanish. Each label can be overridden
+/// with the `set_label` method of [`EpubBuilder`](struct.EpubBuilder.html).
+#[non_exhaustive]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Label {
+    /// Title of the table of contents
+    Toc,
+    /// Heading of the landmarks, in the navigation document
+    Landmarks,
+    /// Title of the generated cover page
+    Cover,
+    /// Title of the generated title page
+    TitlePage,
+    /// Title of the generated copyright page
+    Copyright,
+    /// Title of the generated colophon
+    Colophon,
+    /// Title of the generated endnotes document
+    Notes,
+    /// Title of the generated glossary
+    Glossary,
+    /// Title of the generated dictionary
+    Dictionary,
+    /// Title of the generated bibliography
+    Bibliography,
+    /// Title of the generated index
+    Index,
+    /// "Published by", before the publisher in the copyright page
+    PublishedBy,
+    /// "By", before the authors in the colophon
+    By,
+    /// "Generated with", before the generator in the colophon
+    GeneratedWith,
+}
+
+impl Label {
+    /// Returns the default string for this label, in the given language
+    pub fn default_for(self, lang: &str) -> &'static str {
+        use Label::*;
+        let lang = lang.split(['-', '_']).next().unwrap_or_default();
+        match lang.to_ascii_lowercase().as_str() {
+            "fr" => match self {
+                Toc => "Table des matières",
+                Landmarks => "Repères",
+                Cover => "Couverture",
+                TitlePage => "Page de titre",
+                Copyright => "Copyright",
+                Colophon => "Colophon",
+                Notes => "Notes",
+                Glossary => "Glossaire",
+                Dictionary => "Dictionnaire",
+                Bibliography => "Bibliographie",
+                Index => "Index",
+                PublishedBy => "Publié par",
+                By => "Par",
+                GeneratedWith => "Généré avec",
+            },
+            "de" => match self {
+                Toc => "Inhaltsverzeichnis",
+                Landmarks => "Orientierungspunkte",
+                Cover => "Umschlag",
+                TitlePage => "Titelseite",
+                Copyright => "Impressum",
+                Colophon => "Kolophon",
+                Notes => "Anmerkungen",
+                Glossary => "Glossar",
+                Dictionary => "Wörterbuch",
+                Bibliography => "Literaturverzeichnis",
+                Index => "Register",
+                PublishedBy => "Veröffentlicht von",
+                By => "Von",
+                GeneratedWith => "Erstellt mit",
+            },
+            "es" => match self {
+                Toc => "Índice",
+                Landmarks => "Puntos de referencia",
+                Cover => "Cubierta",
+                TitlePage => "Portada",
+                Copyright => "Derechos de autor",
+                Colophon => "Colofón",
+                Notes => "Notas",
+                Glossary => "Glosario",
+                Dictionary => "Diccionario",
+                Bibliography => "Bibliografía",
+                Index => "Índice alfabético",
+                PublishedBy => "Publicado por",
+                By => "Por",
+                GeneratedWith => "Generado con",
+            },
+            "it" => match self {
+                Toc => "Indice",
+                Landmarks => "Punti di riferimento",
+                Cover => "Copertina",
+                TitlePage => "Frontespizio",
+                Copyright => "Copyright",
+                Colophon => "Colophon",
+                Notes => "Note",
+                Glossary => "Glossario",
+                Dictionary => "Dizionario",
+                Bibliography => "Bibliografia",
+                Index => "Indice analitico",
+                PublishedBy => "Pubblicato da",
+                By => "Di",
+                GeneratedWith => "Generato con",
+            },
+            "pt" => match self {
+                Toc => "Sumário",
+                Landmarks => "Marcos",
+                Cover => "Capa",
+                TitlePage => "Folha de rosto",
+                Copyright => "Direitos autorais",
+                Colophon => "Colofão",
+                Notes => "Notas",
+                Glossary => "Glossário",
+                Dictionary => "Dicionário",
+                Bibliography => "Bibliografia",
+                Index => "Índice remissivo",
+                PublishedBy => "Publicado por",
+                By => "Por",
+                GeneratedWith => "Gerado com",
+            },
+            _ => match self {
+                Toc => "Table Of Contents",
+                Landmarks => "Landmarks",
+                Cover => "Cover",
+                TitlePage => "Title Page",
+                Copyright => "Copyright",
+                Colophon => "Colophon",
+                Notes => "Notes",
+                Glossary => "Glossary",
+                Dictionary => "Dictionary",
+                Bibliography => "Bibliography",
+                Index => "Index",
+                PublishedBy => "Published by",
+                By => "By",
+                GeneratedWith => "Generated with",
+            },
+        }
+    }
+}
+
+#[test]
+fn localized_labels() {
+    assert_eq!(Label::Toc.default_for("en"), "Table Of Contents");
+    assert_eq!(Label::Toc.default_for("fr-CA"), "Table des matières");
+    assert_eq!(Label::Notes.default_for("DE"), "Anmerkungen");
+    assert_eq!(Label::Cover.default_for("x-unknown"), "Cover");
+}
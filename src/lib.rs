@@ -114,6 +114,23 @@
 //! wrappers around both the [Rust zip library](https://crates.io/crates/zip) and calls
 //! to the `zip` command that may (or may not) be installed on your system.
 //!
+//! The `zip-stream` feature (also enabled by default) provides `ZipStream`, which writes
+//! the archive directly to a file or any other writer instead of keeping it in memory,
+//! and `ZipMemory`, which keeps the files in memory so they can be inspected in tests.
+//!
+//! Finally, `ZipDirectory` is always available and writes an unpacked EPUB to a
+//! directory instead of an archive.
+//!
+//! The `async` feature (disabled by default) adds asynchronous versions of the methods
+//! adding content and generating the EPUB, using `tokio`'s `AsyncRead` and `AsyncWrite`
+//! traits.
+//!
+//! The `markdown` feature (disabled by default) adds `EpubContent::from_markdown`, to
+//! write chapters in Markdown instead of XHTML.
+//!
+//! The `html` feature (disabled by default) adds `EpubContent::from_html`, which converts
+//! HTML that may not be well-formed to XHTML.
+//!
 //! It is possible to disable the compilation (and the dependencies) of either of these
 //! wrappers, using `no-default-features`. (If you don't enable at least one of them this
 //! library will be pretty useless).
@@ -124,10 +141,22 @@
 //! version 2.0](https://www.mozilla.org/en-US/MPL/2.0/).
 #![deny(missing_docs)]
 
+mod bibliography;
 mod common;
+mod content_source;
 mod epub;
 mod epub_content;
+mod glossary;
+#[cfg(feature = "html")]
+mod html;
+mod index;
+mod labels;
+#[cfg(feature = "markdown")]
+mod markdown;
+mod notes;
+mod subject;
 mod templates;
+mod title;
 mod toc;
 mod zip;
 #[cfg(feature = "zip-command")]
@@ -135,25 +164,60 @@ mod zip_command;
 #[cfg(feature = "zip-command")]
 #[cfg(feature = "libzip")]
 mod zip_command_or_library;
+mod zip_directory;
+#[cfg(feature = "zip-stream")]
+mod zip_memory;
 #[cfg(feature = "libzip")]
 mod zip_library;
+#[cfg(feature = "zip-stream")]
+mod zip_stream;
 
+pub use bibliography::Citation;
+pub use bibliography::CitationFormatter;
+pub use bibliography::DefaultCitationFormatter;
+pub use content_source::ContentSource;
+pub use content_source::IntoContentSource;
+pub use epub::DeduplicationReport;
 pub use epub::EpubBuilder;
 pub use epub::EpubVersion;
+pub use epub::GeneratedPage;
 pub use epub::MetadataOpf;
 pub use epub::PageDirection;
+pub use epub::ResourceOptions;
+pub use epub::SpinePosition;
 pub use epub_content::EpubContent;
 pub use epub_content::ReferenceType;
+pub use glossary::GlossaryEntry;
+#[cfg(feature = "html")]
+pub use html::HtmlOptions;
+#[cfg(feature = "html")]
+pub use html::HtmlReport;
+pub use index::IndexEntry;
+pub use labels::Label;
+pub use notes::NoteStyle;
 use libzip::result::ZipError;
+pub use subject::Subject;
+pub use subject::SubjectAuthority;
+pub use title::Title;
+pub use title::TitleType;
 pub use toc::Toc;
 pub use toc::TocElement;
+pub use zip::Compression;
+pub use zip::Zip;
+#[cfg(feature = "async")]
+pub use zip::AsyncZip;
 #[cfg(feature = "zip-command")]
 pub use zip_command::ZipCommand;
 #[cfg(feature = "zip-command")]
 #[cfg(feature = "libzip")]
 pub use zip_command_or_library::ZipCommandOrLibrary;
+pub use zip_directory::ZipDirectory;
+#[cfg(feature = "zip-stream")]
+pub use zip_memory::ZipMemory;
 #[cfg(feature = "libzip")]
 pub use zip_library::ZipLibrary;
+#[cfg(feature = "zip-stream")]
+pub use zip_stream::ZipStream;
 
 /// Error type of this crate. Each variant represent a type of event that may happen during this crate's operations.
 #[derive(thiserror::Error, Debug)]
@@ -198,6 +262,38 @@ pub enum Error {
     /// An error returned when an invalid [`Path`] has been encountered during epub processing.
     #[error("Invalid path: {0}")]
     InvalidPath(String),
+    /// An error returned when no file added to the EPUB matches an href.
+    #[error("No file found at href: {0}")]
+    HrefNotFound(String),
+    /// An error returned when adding a file at a path that is already used.
+    #[error("A file was already added at path: {0}")]
+    DuplicatePath(String),
+    /// An error returned when adding a file at a path that only differs by case from an
+    /// existing one, which breaks on case-insensitive file systems.
+    #[error("Path {path} collides with existing path {existing}")]
+    PathCollision {
+        /// The path of the file that was added.
+        path: String,
+        /// The path of the existing file.
+        existing: String,
+    },
+    /// An error returned when an id given for a file is not a valid XML id.
+    #[error("Invalid id: {0}")]
+    InvalidId(String),
+    /// An error returned when an id given for a file is already used in the manifest.
+    #[error("Id is already used: {0}")]
+    DuplicateId(String),
+    /// An error returned when a subject code does not have the format of its authority.
+    #[error("Invalid {authority} subject code: {code}")]
+    InvalidSubjectCode {
+        /// The authority of the code.
+        authority: String,
+        /// The invalid code.
+        code: String,
+    },
+    /// An error returned when a deflate level is not between 1 and 9.
+    #[error("Invalid compression level: {0}")]
+    InvalidCompressionLevel(u8),
 }
 
 impl From<std::io::Error> for Error {
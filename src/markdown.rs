@@ -0,0 +1,169 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with
+// this file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use crate::common;
+use crate::ContentSource;
+use crate::EpubContent;
+use crate::TocElement;
+
+use pulldown_cmark::{html, CowStr, Event, HeadingLevel, Options, Parser, Tag, TagEnd};
+use std::collections::HashSet;
+use std::path::Path;
+
+impl EpubContent<ContentSource> {
+    /// Creates a content from Markdown.
+    ///
+    /// The Markdown is converted to XHTML and, like for any body fragment, the builder
+    /// wraps it in a complete page with the language and stylesheet of the book.
+    ///
+    /// The first heading gives the title of the content; the following ones are added
+    /// as its children in the table of contents. Headings get an id derived from their
+    /// text, unless one is set with the `{#id}` syntax.
+    ///
+    /// This also returns the paths of the local images that are referenced, relative to
+    /// the `OEBPS` directory, so they can be added with `add_resource`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use epub_builder::{ContentSource, EpubContent};
+    ///
+    /// let markdown = "# Chapter 1\n\n![Map](../images/map.png)\n\n## Section 1 {#s1}\n\nText";
+    /// let (content, images) = EpubContent::from_markdown("text/chapter_1.xhtml", markdown);
+    /// assert_eq!(content.toc.title, "Chapter 1");
+    /// assert_eq!(content.toc.children[0].url, "text/chapter_1.xhtml#s1");
+    /// assert_eq!(images, ["images/map.png"]);
+    /// ```
+    pub fn from_markdown<S: Into<String>>(href: S, markdown: &str) -> (Self, Vec<String>) {
+        let href = href.into();
+        let options = Options::ENABLE_TABLES
+            | Options::ENABLE_FOOTNOTES
+            | Options::ENABLE_STRIKETHROUGH
+            | Options::ENABLE_HEADING_ATTRIBUTES;
+        let mut events: Vec<Event> = Parser::new_ext(markdown, options).collect();
+
+        let mut headings: Vec<(HeadingLevel, String, String)> = vec![];
+        let mut images: Vec<String> = vec![];
+        let mut ids: HashSet<String> = HashSet::new();
+        // Index of the start event of the current heading, and its text
+        let mut current: Option<(usize, String)> = None;
+        for i in 0..events.len() {
+            match &events[i] {
+                Event::Start(Tag::Heading { .. }) => current = Some((i, String::new())),
+                Event::Text(text) | Event::Code(text) => {
+                    if let Some((_, ref mut title)) = current {
+                        title.push_str(text);
+                    }
+                }
+                Event::End(TagEnd::Heading(_)) => {
+                    if let Some((start, title)) = current.take() {
+                        if let Event::Start(Tag::Heading { level, id, .. }) = &mut events[start] {
+                            let unique = unique_id(id.as_deref().unwrap_or(&slug(&title)), &ids);
+                            ids.insert(unique.clone());
+                            *id = Some(CowStr::from(unique.clone()));
+                            headings.push((*level, unique, title));
+                        }
+                    }
+                }
+                Event::Start(Tag::Image { dest_url, .. }) => {
+                    if let Some(image) = local_path(&href, dest_url) {
+                        if !images.contains(&image) {
+                            images.push(image);
+                        }
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        let mut body = String::new();
+        html::push_html(&mut body, events.into_iter());
+
+        let mut content = EpubContent::from_fragment(href.as_str(), ContentSource::bytes(body));
+        let mut headings = headings.into_iter();
+        if let Some((top, _, title)) = headings.next() {
+            content = content.title(title);
+            for (level, id, title) in headings {
+                let level = (level as i32 - top as i32 + 1).max(2);
+                content
+                    .toc
+                    .add(TocElement::new(format!("{href}#{id}"), title).level(level));
+            }
+        }
+        (content, images)
+    }
+}
+
+/// Returns the path of an image relative to the `OEBPS` directory, if it is local
+fn local_path(href: &str, url: &str) -> Option<String> {
+    if url.is_empty() || url.contains(':') || url.starts_with('#') || url.starts_with("//") {
+        return None;
+    }
+    let url = url.split(['#', '?']).next().unwrap_or(url);
+    let dir = Path::new(href).parent().unwrap_or(Path::new(""));
+    common::normalize_path(&dir.join(url)).ok()
+}
+
+/// Turns the text of a heading into an id
+fn slug(text: &str) -> String {
+    let mut slug = String::new();
+    for c in text.trim().chars() {
+        if c.is_alphanumeric() {
+            slug.extend(c.to_lowercase());
+        } else if !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let slug = slug.trim_matches('-');
+    if slug.is_empty() || slug.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("section-{slug}").trim_end_matches('-').to_string()
+    } else {
+        slug.to_string()
+    }
+}
+
+/// Adds a suffix to `id` if it is already used in the page
+fn unique_id(id: &str, ids: &HashSet<String>) -> String {
+    let mut unique = id.to_string();
+    let mut suffix = 1;
+    while ids.contains(&unique) {
+        suffix += 1;
+        unique = format!("{id}-{suffix}");
+    }
+    unique
+}
+
+#[test]
+fn markdown_headings() {
+    let markdown = "# Chapter\n\n## A section\n\n### Sub\n\n## A section\n\n## 2nd {#second}";
+    let (content, images) = EpubContent::from_markdown("chapter.xhtml", markdown);
+    assert!(images.is_empty());
+    assert!(content.fragment);
+    assert_eq!(content.toc.title, "Chapter");
+    let children: Vec<_> = content
+        .toc
+        .children
+        .iter()
+        .map(|child| child.url.as_str())
+        .collect();
+    assert_eq!(
+        children,
+        [
+            "chapter.xhtml#a-section",
+            "chapter.xhtml#a-section-2",
+            "chapter.xhtml#second"
+        ]
+    );
+    assert_eq!(content.toc.children[0].children[0].url, "chapter.xhtml#sub");
+
+    let body = String::from_utf8(content.content.read_to_vec().unwrap()).unwrap();
+    assert!(body.contains("<h2 id=\"a-section-2\">A section</h2>"));
+}
+
+#[test]
+fn markdown_images() {
+    let markdown = "![a](a.png) ![b](https://example.org/b.png) ![c](../c.jpg#x) ![a](a.png)";
+    let (_, images) = EpubContent::from_markdown("text/chapter.xhtml", markdown);
+    assert_eq!(images, ["text/a.png", "c.jpg"]);
+}
@@ -0,0 +1,147 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with
+// this file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use crate::common;
+use crate::EpubVersion;
+
+/// Path of the generated endnotes document
+pub(crate) const ENDNOTES_FILE: &str = "notes.xhtml";
+
+/// How the notes added with the `add_note` method of
+/// [`EpubBuilder`](struct.EpubBuilder.html) are displayed.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum NoteStyle {
+    /// Notes are written at the end of the chapter they belong to
+    #[default]
+    Footnotes,
+    /// Notes are all written in a generated `notes.xhtml` document, at the end of the book
+    Endnotes,
+}
+
+/// A note added to the builder
+#[derive(Debug)]
+pub(crate) struct Note {
+    /// Number of the note, starting at 1
+    pub number: usize,
+    /// Href of the chapter the note belongs to
+    pub chapter: String,
+    /// XHTML content of the note
+    pub content: String,
+}
+
+/// Renders the link to a note, to insert in the chapter
+pub(crate) fn render_noteref(version: EpubVersion, style: NoteStyle, note: &Note) -> String {
+    let href = match style {
+        NoteStyle::Footnotes => format!("#note-{}", note.number),
+        NoteStyle::Endnotes => format!(
+            "{}#note-{}",
+            common::encode_href(&common::relative_href(&note.chapter, ENDNOTES_FILE)),
+            note.number
+        ),
+    };
+    let semantics = match version {
+        EpubVersion::V20 => "class=\"noteref\"",
+        EpubVersion::V30 => "epub:type=\"noteref\" role=\"doc-noteref\"",
+    };
+    format!(
+        "<sup><a {semantics} id=\"noteref-{number}\" href=\"{href}\">{number}</a></sup>",
+        number = note.number,
+        href = html_escape::encode_double_quoted_attribute(&href),
+    )
+}
+
+/// Renders the notes of a chapter, to insert at the end of its body
+pub(crate) fn render_footnotes<'a, I>(version: EpubVersion, notes: I) -> String
+where
+    I: Iterator<Item = &'a Note>,
+{
+    notes
+        .map(|note| {
+            let (element, semantics, backlink) = match version {
+                EpubVersion::V20 => ("div", "class=\"footnote\"", ""),
+                EpubVersion::V30 => (
+                    "aside",
+                    "epub:type=\"footnote\" role=\"doc-footnote\"",
+                    " role=\"doc-backlink\"",
+                ),
+            };
+            format!(
+                "<{element} {semantics} id=\"note-{number}\">\n  \
+                 <a href=\"#noteref-{number}\"{backlink}>{number}.</a>\n{content}\n</{element}>",
+                number = note.number,
+                content = common::indent(&note.content, 1), // Not escaped: XML content
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Renders the body of the endnotes document, with `title`, which must already be escaped
+pub(crate) fn render_endnotes<'a, I>(version: EpubVersion, title: &str, notes: I) -> String
+where
+    I: Iterator<Item = &'a Note>,
+{
+    let (semantics, note_semantics, backlink) = match version {
+        EpubVersion::V20 => ("class=\"endnotes\"", "class=\"endnote\"", ""),
+        EpubVersion::V30 => (
+            "epub:type=\"endnotes\" role=\"doc-endnotes\"",
+            "epub:type=\"endnote\" role=\"doc-endnote\"",
+            " role=\"doc-backlink\"",
+        ),
+    };
+    let items: Vec<String> = notes
+        .map(|note| {
+            format!(
+                "<li {note_semantics} id=\"note-{number}\">\n{content}\n  \
+                 <a href=\"{href}#noteref-{number}\"{backlink}>↩</a>\n</li>",
+                number = note.number,
+                content = common::indent(&note.content, 1), // Not escaped: XML content
+                href = html_escape::encode_double_quoted_attribute(&common::encode_href(
+                    &common::relative_href(ENDNOTES_FILE, &note.chapter)
+                )),
+            )
+        })
+        .collect();
+    let element = match version {
+        EpubVersion::V20 => "div",
+        EpubVersion::V30 => "section",
+    };
+    format!(
+        "<{element} {semantics}>\n  <h1>{title}</h1>\n  <ol>\n{items}\n  </ol>\n</{element}>",
+        items = common::indent(items.join("\n"), 2), // Not escaped: XML content
+    )
+}
+
+/// Inserts `notes` at the end of the body of a complete XHTML document
+pub(crate) fn insert_in_body(document: &mut String, notes: &str) {
+    let position = document.rfind("</body>").unwrap_or(document.len());
+    document.insert_str(position, &format!("{notes}\n"));
+}
+
+#[test]
+fn notes_markup() {
+    let note = Note {
+        number: 2,
+        chapter: String::from("text/chapter.xhtml"),
+        content: String::from("<p>Note</p>"),
+    };
+    assert_eq!(
+        render_noteref(EpubVersion::V30, NoteStyle::Endnotes, &note),
+        "<sup><a epub:type=\"noteref\" role=\"doc-noteref\" id=\"noteref-2\" \
+         href=\"../notes.xhtml#note-2\">2</a></sup>"
+    );
+    assert_eq!(
+        render_noteref(EpubVersion::V20, NoteStyle::Footnotes, &note),
+        "<sup><a class=\"noteref\" id=\"noteref-2\" href=\"#note-2\">2</a></sup>"
+    );
+    assert_eq!(
+        render_footnotes(EpubVersion::V30, [note].iter()),
+        "<aside epub:type=\"footnote\" role=\"doc-footnote\" id=\"note-2\">\n  \
+         <a href=\"#noteref-2\" role=\"doc-backlink\">2.</a>\n  <p>Note</p>\n</aside>"
+    );
+
+    let mut document = String::from("<html><body><p>Text</p></body></html>");
+    insert_in_body(&mut document, "<aside/>");
+    assert_eq!(document, "<html><body><p>Text</p><aside/>\n</body></html>");
+}
@@ -0,0 +1,229 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with
+// this file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use crate::common;
+use crate::EpubVersion;
+use crate::Result;
+
+/// The authority, or classification scheme, of a subject code.
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SubjectAuthority {
+    /// BISAC Subject Headings, e.g. `FIC000000`
+    Bisac,
+    /// Thema subject categories and qualifiers, e.g. `FBA` or `1DDF-FR-A`
+    Thema,
+    /// Library of Congress Subject Headings, e.g. `sh85048050`
+    Lcsh,
+    /// Another authority, given by its name or URL. Codes are not validated.
+    Other(String),
+}
+
+impl SubjectAuthority {
+    /// Name of the authority, as written in the package document
+    fn as_str(&self) -> &str {
+        match self {
+            SubjectAuthority::Bisac => "BISAC",
+            SubjectAuthority::Thema => "THEMA",
+            SubjectAuthority::Lcsh => "LCSH",
+            SubjectAuthority::Other(name) => name,
+        }
+    }
+
+    /// Tells whether `code` has the format of a code of this authority
+    fn is_valid_code(&self, code: &str) -> bool {
+        let bytes = code.as_bytes();
+        match self {
+            // Three letters and six digits
+            SubjectAuthority::Bisac => {
+                bytes.len() == 9
+                    && bytes[..3].iter().all(u8::is_ascii_uppercase)
+                    && bytes[3..].iter().all(u8::is_ascii_digit)
+            }
+            // Subject categories are letters, qualifiers start with a digit from 1 to 6,
+            // possibly followed by letters, digits and hyphens (e.g. for regions)
+            SubjectAuthority::Thema => match bytes.first() {
+                Some(b'A'..=b'Z') => bytes.len() <= 6 && bytes.iter().all(u8::is_ascii_uppercase),
+                Some(b'1'..=b'6') => {
+                    bytes.len() <= 16
+                        && !code.ends_with('-')
+                        && !code.contains("--")
+                        && bytes[1..]
+                            .iter()
+                            .all(|&b| b.is_ascii_uppercase() || b.is_ascii_digit() || b == b'-')
+                }
+                _ => false,
+            },
+            // "sh" and eight or ten digits
+            SubjectAuthority::Lcsh => {
+                code.starts_with("sh")
+                    && matches!(bytes.len(), 10 | 12)
+                    && bytes[2..].iter().all(u8::is_ascii_digit)
+            }
+            SubjectAuthority::Other(_) => true,
+        }
+    }
+}
+
+/// A subject of the book, possibly identified by a code from a classification scheme,
+/// added with the `add_structured_subject` method of
+/// [`EpubBuilder`](struct.EpubBuilder.html).
+///
+/// In EPUB 3, the code is written as `authority` and `term` refinements of the subject;
+/// in EPUB 2, as a subject with an `opf:scheme` attribute.
+///
+/// # Example
+///
+/// ```
+/// use epub_builder::{Subject, SubjectAuthority};
+///
+/// let subject = Subject::new("FICTION / Science Fiction / General")
+///     .code(SubjectAuthority::Bisac, "FIC028000");
+/// assert!(subject.validate().is_ok());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Subject {
+    /// The subject, as displayed
+    pub text: String,
+    /// The authority and code of the subject in its scheme
+    pub code: Option<(SubjectAuthority, String)>,
+    /// Language of the text, if it differs from the language of the book
+    pub lang: Option<String>,
+}
+
+impl Subject {
+    /// Creates a new subject, with no code
+    pub fn new<S: Into<String>>(text: S) -> Self {
+        Subject {
+            text: text.into(),
+            code: None,
+            lang: None,
+        }
+    }
+
+    /// Sets the language of the text
+    pub fn lang<S: Into<String>>(mut self, lang: S) -> Self {
+        self.lang = Some(lang.into());
+        self
+    }
+
+    /// Sets the code of the subject in the scheme of an authority
+    pub fn code<S: Into<String>>(mut self, authority: SubjectAuthority, code: S) -> Self {
+        self.code = Some((authority, code.into()));
+        self
+    }
+
+    /// Checks that the code has the format of its authority
+    ///
+    /// This only checks the format, not that the code actually exists. Codes of
+    /// `SubjectAuthority::Other` are always valid.
+    pub fn validate(&self) -> Result<()> {
+        match self.code {
+            Some((ref authority, ref code)) if !authority.is_valid_code(code) => {
+                Err(crate::Error::InvalidSubjectCode {
+                    authority: authority.as_str().to_string(),
+                    code: code.clone(),
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Renders the `dc:subject` elements of a subject, numbered `number` (for ids)
+pub(crate) fn render_subject(
+    version: EpubVersion,
+    subject: &Subject,
+    number: usize,
+    escape_html: bool,
+) -> Vec<String> {
+    let text = common::encode_html(&subject.text, escape_html);
+    let lang_attr = match subject.lang {
+        Some(ref lang) => format!(
+            " xml:lang=\"{}\"",
+            html_escape::encode_double_quoted_attribute(lang)
+        ),
+        None => String::new(),
+    };
+    let Some((ref authority, ref code)) = subject.code else {
+        return vec![format!("<dc:subject{lang_attr}>{text}</dc:subject>")];
+    };
+    match version {
+        EpubVersion::V20 => {
+            let mut lines = vec![format!(
+                "<dc:subject opf:scheme=\"{authority}\">{code}</dc:subject>",
+                authority = html_escape::encode_double_quoted_attribute(authority.as_str()),
+                code = html_escape::encode_text(code),
+            )];
+            if !subject.text.is_empty() {
+                lines.push(format!("<dc:subject{lang_attr}>{text}</dc:subject>"));
+            }
+            lines
+        }
+        EpubVersion::V30 => {
+            let id = format!("epub-subject-{number}");
+            // The subject can't be empty, so the code is used if there is no text
+            let text = match subject.text.as_str() {
+                "" => html_escape::encode_text(code),
+                _ => text,
+            };
+            vec![
+                format!("<dc:subject id=\"{id}\"{lang_attr}>{text}</dc:subject>"),
+                format!(
+                    "<meta refines=\"#{id}\" property=\"authority\">{}</meta>",
+                    html_escape::encode_text(authority.as_str())
+                ),
+                format!(
+                    "<meta refines=\"#{id}\" property=\"term\">{}</meta>",
+                    html_escape::encode_text(code)
+                ),
+            ]
+        }
+    }
+}
+
+#[test]
+fn subject_codes() {
+    let valid = |authority: SubjectAuthority, code: &str| {
+        Subject::new("").code(authority, code).validate().is_ok()
+    };
+    assert!(valid(SubjectAuthority::Bisac, "FIC028000"));
+    assert!(!valid(SubjectAuthority::Bisac, "FIC28000"));
+    assert!(!valid(SubjectAuthority::Bisac, "fic028000"));
+    assert!(valid(SubjectAuthority::Thema, "FBA"));
+    assert!(valid(SubjectAuthority::Thema, "1DDF-FR-A"));
+    assert!(!valid(SubjectAuthority::Thema, "1DDF-"));
+    assert!(!valid(SubjectAuthority::Thema, "FB1"));
+    assert!(valid(SubjectAuthority::Lcsh, "sh85048050"));
+    assert!(valid(SubjectAuthority::Lcsh, "sh2007001234"));
+    assert!(!valid(SubjectAuthority::Lcsh, "sh850480501"));
+    assert!(!valid(SubjectAuthority::Lcsh, "85048050"));
+    assert!(valid(SubjectAuthority::Other("Local".into()), "anything"));
+
+    let subject = Subject::new("Fiction").code(SubjectAuthority::Bisac, "FIC000000");
+    assert_eq!(
+        render_subject(EpubVersion::V20, &subject, 1, true),
+        [
+            "<dc:subject opf:scheme=\"BISAC\">FIC000000</dc:subject>",
+            "<dc:subject>Fiction</dc:subject>"
+        ]
+    );
+    assert_eq!(
+        render_subject(EpubVersion::V30, &Subject::new("Roman").lang("fr"), 1, true),
+        ["<dc:subject xml:lang=\"fr\">Roman</dc:subject>"]
+    );
+    assert_eq!(
+        render_subject(EpubVersion::V30, &subject, 1, true),
+        [
+            "<dc:subject id=\"epub-subject-1\">Fiction</dc:subject>",
+            "<meta refines=\"#epub-subject-1\" property=\"authority\">BISAC</meta>",
+            "<meta refines=\"#epub-subject-1\" property=\"term\">FIC000000</meta>"
+        ]
+    );
+    let subject = Subject::new("").code(SubjectAuthority::Thema, "FBA");
+    assert_eq!(
+        render_subject(EpubVersion::V30, &subject, 2, true)[0],
+        "<dc:subject id=\"epub-subject-2\">FBA</dc:subject>"
+    );
+}
@@ -7,7 +7,7 @@ use once_cell::sync::Lazy;
 pub static IBOOKS: &[u8] = include_bytes!("../templates/ibooks.xml");
 pub static CONTAINER: &[u8] = include_bytes!("../templates/container.xml");
 
-static ENGINE: Lazy<::upon::Engine> = Lazy::new(|| {
+pub static ENGINE: Lazy<::upon::Engine> = Lazy::new(|| {
     let mut engine = ::upon::Engine::new();
     engine.add_filter("eq", str::eq);
     engine
@@ -19,6 +19,30 @@ pub static TOC_NCX: Lazy<::upon::Template> = Lazy::new(|| {
         .expect("error compiling 'toc.ncx' template'")
 });
 
+pub static COVER_XHTML: Lazy<::upon::Template> = Lazy::new(|| {
+    ENGINE
+        .compile(include_str!("../templates/cover.xhtml"))
+        .expect("error compiling 'cover.xhtml' template")
+});
+
+pub static TITLE_PAGE_XHTML: Lazy<::upon::Template> = Lazy::new(|| {
+    ENGINE
+        .compile(include_str!("../templates/title_page.xhtml"))
+        .expect("error compiling 'title_page.xhtml' template")
+});
+
+pub static COPYRIGHT_XHTML: Lazy<::upon::Template> = Lazy::new(|| {
+    ENGINE
+        .compile(include_str!("../templates/copyright.xhtml"))
+        .expect("error compiling 'copyright.xhtml' template")
+});
+
+pub static COLOPHON_XHTML: Lazy<::upon::Template> = Lazy::new(|| {
+    ENGINE
+        .compile(include_str!("../templates/colophon.xhtml"))
+        .expect("error compiling 'colophon.xhtml' template")
+});
+
 pub mod v2 {
     use crate::templates::ENGINE;
     use once_cell::sync::Lazy;
@@ -33,6 +57,11 @@ pub mod v2 {
             .compile(include_str!("../templates/v2/nav.xhtml"))
             .expect("error compiling 'nav.xhtml' (for EPUB 2.0) template")
     });
+    pub static PAGE_XHTML: Lazy<::upon::Template> = Lazy::new(|| {
+        ENGINE
+            .compile(include_str!("../templates/v2/page.xhtml"))
+            .expect("error compiling 'page.xhtml' (for EPUB 2.0) template")
+    });
 }
 pub mod v3 {
     use crate::templates::ENGINE;
@@ -48,4 +77,9 @@ pub mod v3 {
             .compile(include_str!("../templates/v3/nav.xhtml"))
             .expect("error compiling 'nav.xhtml' (for EPUB 3.0) template")
     });
+    pub static PAGE_XHTML: Lazy<::upon::Template> = Lazy::new(|| {
+        ENGINE
+            .compile(include_str!("../templates/v3/page.xhtml"))
+            .expect("error compiling 'page.xhtml' (for EPUB 3.0) template")
+    });
 }
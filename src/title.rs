@@ -0,0 +1,254 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with
+// this file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use crate::common;
+use crate::EpubVersion;
+
+/// The type of a title, written as a `title-type` refinement in EPUB 3.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TitleType {
+    /// Main title
+    Main,
+    /// Subtitle
+    Subtitle,
+    /// Short version of the title
+    Short,
+    /// Title of the collection the book belongs to
+    Collection,
+    /// Edition statement
+    Edition,
+    /// Extended version of the title
+    Expanded,
+}
+
+impl TitleType {
+    fn as_str(self) -> &'static str {
+        match self {
+            TitleType::Main => "main",
+            TitleType::Subtitle => "subtitle",
+            TitleType::Short => "short",
+            TitleType::Collection => "collection",
+            TitleType::Edition => "edition",
+            TitleType::Expanded => "expanded",
+        }
+    }
+}
+
+/// A title of the book, added with the `add_title` method of
+/// [`EpubBuilder`](struct.EpubBuilder.html).
+///
+/// # Example
+///
+/// ```
+/// use epub_builder::{Title, TitleType};
+///
+/// let title = Title::new("Le Petit Prince")
+///     .lang("fr")
+///     .title_type(TitleType::Main)
+///     .display_seq(2);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Title {
+    /// The text of the title
+    pub text: String,
+    /// Language of the title, if it differs from the language of the book
+    pub lang: Option<String>,
+    /// Type of the title
+    pub title_type: Option<TitleType>,
+    /// Position of the title when reading systems display all of them, starting at 1
+    pub display_seq: Option<u32>,
+}
+
+impl Title {
+    /// Creates a new title
+    pub fn new<S: Into<String>>(text: S) -> Self {
+        Title {
+            text: text.into(),
+            lang: None,
+            title_type: None,
+            display_seq: None,
+        }
+    }
+
+    /// Sets the language of the title (e.g. `"en"`)
+    pub fn lang<S: Into<String>>(mut self, lang: S) -> Self {
+        self.lang = Some(lang.into());
+        self
+    }
+
+    /// Sets the type of the title
+    pub fn title_type(mut self, title_type: TitleType) -> Self {
+        self.title_type = Some(title_type);
+        self
+    }
+
+    /// Sets the position of the title when all titles are displayed
+    pub fn display_seq(mut self, display_seq: u32) -> Self {
+        self.display_seq = Some(display_seq);
+        self
+    }
+}
+
+/// Chooses the main title among `titles`, for a book in `lang`
+///
+/// This is the first title of type `Main` (or without type) in the language of the book,
+/// or else the first one of type `Main` (or without type), or else the first title.
+pub(crate) fn main_title<'a>(titles: &'a [Title], lang: &str) -> Option<&'a Title> {
+    let is_main = |title: &&Title| matches!(title.title_type, None | Some(TitleType::Main));
+    let in_lang = |title: &&Title| match title.lang {
+        Some(ref title_lang) => title_lang.eq_ignore_ascii_case(lang),
+        None => true,
+    };
+    titles
+        .iter()
+        .filter(is_main)
+        .find(in_lang)
+        .or_else(|| titles.iter().find(is_main))
+        .or_else(|| {
+            // A subtitle or an edition can't stand for the title of the book
+            titles.iter().find(|title| {
+                !matches!(
+                    title.title_type,
+                    Some(TitleType::Subtitle) | Some(TitleType::Edition)
+                )
+            })
+        })
+}
+
+/// Returns the titles for EPUB 2, where subtitles and editions are not written as separate
+/// titles (reading systems would not know what they are), but appended to the main title
+/// when they are in the same language, e.g. `Main: Subtitle (Second edition)`
+fn epub2_titles(titles: &[Title]) -> Vec<Title> {
+    let Some((main, others)) = titles.split_first() else {
+        return vec![];
+    };
+    let mut main = main.clone();
+    let mut titles = vec![];
+    let mut editions = vec![];
+    let same_lang = |title: &Title| title.lang.is_none() || title.lang == main.lang;
+    for title in others {
+        match title.title_type {
+            Some(TitleType::Subtitle) if same_lang(title) && main.text.is_empty() => {
+                main.text = title.text.clone();
+            }
+            Some(TitleType::Subtitle) if same_lang(title) => {
+                main.text = format!("{}: {}", main.text, title.text);
+            }
+            Some(TitleType::Edition) if same_lang(title) => editions.push(title.text.as_str()),
+            Some(TitleType::Subtitle) | Some(TitleType::Edition) => (),
+            _ => titles.push(title.clone()),
+        }
+    }
+    if !editions.is_empty() {
+        main.text = format!("{} ({})", main.text, editions.join(", "));
+    }
+    titles.insert(0, main);
+    titles
+}
+
+/// Renders the `dc:title` elements of the package document, with their refinements in
+/// EPUB 3; the main title must come first
+pub(crate) fn render_titles(version: EpubVersion, titles: &[Title], escape_html: bool) -> String {
+    let titles = match version {
+        EpubVersion::V20 => epub2_titles(titles),
+        EpubVersion::V30 => titles.to_vec(),
+    };
+    // If there are titles of other types, the main title must be identified as such
+    let has_types = titles
+        .iter()
+        .any(|title| !matches!(title.title_type, None | Some(TitleType::Main)));
+    let mut lines = vec![];
+    for (i, title) in titles.iter().enumerate() {
+        let id = format!("epub-title-{}", i + 1);
+        let refinements = match version {
+            EpubVersion::V20 => vec![],
+            EpubVersion::V30 => {
+                let mut refinements = vec![];
+                let title_type = match title.title_type {
+                    None if i == 0 && has_types => Some(TitleType::Main),
+                    title_type => title_type,
+                };
+                if let Some(title_type) = title_type {
+                    refinements.push(("title-type", title_type.as_str().to_string()));
+                }
+                if let Some(display_seq) = title.display_seq {
+                    refinements.push(("display-seq", display_seq.to_string()));
+                }
+                refinements
+            }
+        };
+        lines.push(format!(
+            "<dc:title{id_attr}{lang_attr}>{text}</dc:title>",
+            id_attr = if refinements.is_empty() {
+                String::new()
+            } else {
+                format!(" id=\"{id}\"")
+            },
+            lang_attr = match title.lang {
+                Some(ref lang) => format!(
+                    " xml:lang=\"{}\"",
+                    html_escape::encode_double_quoted_attribute(lang)
+                ),
+                None => String::new(),
+            },
+            text = common::encode_html(&title.text, escape_html),
+        ));
+        for (property, value) in refinements {
+            lines.push(format!(
+                "<meta refines=\"#{id}\" property=\"{property}\">{value}</meta>"
+            ));
+        }
+    }
+    lines.join("\n")
+}
+
+#[test]
+fn choose_main_title() {
+    let titles = vec![
+        Title::new("Sub").title_type(TitleType::Subtitle),
+        Title::new("Title").lang("en").title_type(TitleType::Main),
+        Title::new("Titre").lang("fr").title_type(TitleType::Main),
+    ];
+    assert_eq!(main_title(&titles, "fr").unwrap().text, "Titre");
+    assert_eq!(main_title(&titles, "de").unwrap().text, "Title");
+    assert!(main_title(&titles[..1], "en").is_none());
+    let short = Title::new("Short").title_type(TitleType::Short);
+    assert_eq!(
+        main_title(&[titles[0].clone(), short], "en").unwrap().text,
+        "Short"
+    );
+    assert!(main_title(&[], "en").is_none());
+}
+
+#[test]
+fn subtitle_and_edition() {
+    let titles = vec![
+        Title::new("Main"),
+        Title::new("Sub").title_type(TitleType::Subtitle),
+        Title::new("Untertitel")
+            .lang("de")
+            .title_type(TitleType::Subtitle),
+        Title::new("2nd edition").title_type(TitleType::Edition),
+    ];
+    assert_eq!(
+        render_titles(EpubVersion::V20, &titles, true),
+        "<dc:title>Main: Sub (2nd edition)</dc:title>"
+    );
+    // Without a main title, the subtitle is not appended to an empty title
+    assert_eq!(
+        render_titles(EpubVersion::V20, &[Title::new(""), titles[1].clone()], true),
+        "<dc:title>Sub</dc:title>"
+    );
+    assert_eq!(
+        render_titles(EpubVersion::V30, &titles, true),
+        "<dc:title id=\"epub-title-1\">Main</dc:title>
+<meta refines=\"#epub-title-1\" property=\"title-type\">main</meta>
+<dc:title id=\"epub-title-2\">Sub</dc:title>
+<meta refines=\"#epub-title-2\" property=\"title-type\">subtitle</meta>
+<dc:title id=\"epub-title-3\" xml:lang=\"de\">Untertitel</dc:title>
+<meta refines=\"#epub-title-3\" property=\"title-type\">subtitle</meta>
+<dc:title id=\"epub-title-4\">2nd edition</dc:title>
+<meta refines=\"#epub-title-4\" property=\"title-type\">edition</meta>"
+    );
+}
@@ -23,6 +23,8 @@ pub struct TocElement {
     pub title: String,
     /// Title of this entry without HTML tags (if None, defaults to the main one)
     pub raw_title: Option<String>,
+    /// Language of the title, if it differs from the language of the book
+    pub lang: Option<String>,
     /// Inner elements
     pub children: Vec<TocElement>,
 }
@@ -37,6 +39,7 @@ impl TocElement {
             url: url.into(),
             title: title.into(),
             raw_title: None,
+            lang: None,
             children: vec![],
         }
     }
@@ -49,6 +52,26 @@ impl TocElement {
         self
     }
 
+    /// Sets the language of the title, for multilingual books (e.g. `"en"`)
+    pub fn lang<S: Into<String>>(mut self, lang: S) -> TocElement {
+        self.lang = Some(lang.into());
+        self
+    }
+
+    /// Returns the `xml:lang` attribute (preceded by a space) of the entry, if any
+    ///
+    /// `xml:lang` is used both in the NCX and in the navigation documents, where it is valid
+    /// in XHTML 1.1 as well as in the XML syntax of HTML5.
+    fn lang_attr(&self) -> String {
+        match self.lang {
+            Some(ref lang) => format!(
+                " xml:lang=\"{}\"",
+                html_escape::encode_double_quoted_attribute(lang)
+            ),
+            None => String::new(),
+        }
+    }
+
     /// Sets the level of a TocElement
     pub fn level(mut self, level: i32) -> Self {
         self.level = level;
@@ -110,6 +133,7 @@ impl TocElement {
 
     /// Render element for Epub's toc.ncx format
     #[doc(hidden)]
+    #[allow(clippy::only_used_in_recursion)]
     pub fn render_epub(&self, mut offset: u32, escape_html: bool) -> (u32, String) {
         offset += 1;
         let id = offset;
@@ -134,14 +158,15 @@ impl TocElement {
             format!(
                 "\
 <navPoint playOrder=\"{id}\" id=\"navPoint-{id}\">
-  <navLabel>
+  <navLabel{lang}>
    <text>{title}</text>
   </navLabel>
   <content src=\"{url}\"/>{children}
 </navPoint>",
                 id = html_escape::encode_double_quoted_attribute(&id.to_string()),
+                lang = self.lang_attr(), // Escaped above
                 title = title.trim(),
-                url = html_escape::encode_double_quoted_attribute(&self.url),
+                url = html_escape::encode_double_quoted_attribute(&common::encode_url(&self.url)),
                 children = children, // Not escaped: XML content
             ),
         )
@@ -155,8 +180,9 @@ impl TocElement {
         }
         if self.children.is_empty() {
             format!(
-                "<li><a href=\"{link}\">{title}</a></li>",
-                link = html_escape::encode_double_quoted_attribute(&self.url),
+                "<li><a href=\"{link}\"{lang}>{title}</a></li>",
+                link = html_escape::encode_double_quoted_attribute(&common::encode_url(&self.url)),
+                lang = self.lang_attr(), // Escaped above
                 title = common::encode_html(&self.title, escape_html),
             )
         } else {
@@ -172,10 +198,11 @@ impl TocElement {
             format!(
                 "\
 <li>
-  <a href=\"{link}\">{title}</a>
+  <a href=\"{link}\"{lang}>{title}</a>
 {children}
 </li>",
-                link = html_escape::encode_double_quoted_attribute(&self.url),
+                link = html_escape::encode_double_quoted_attribute(&common::encode_url(&self.url)),
+                lang = self.lang_attr(), // Escaped above
                 title = common::encode_html(&self.title, escape_html),
                 children = common::indent(children, 1), // Not escaped: XML content
             )
@@ -4,18 +4,101 @@
 
 use std::io::Read;
 use std::io::Write;
+use std::path::Component;
 use std::path::Path;
 
 use crate::Result;
 
 /// An abstraction over possible Zip implementations.
 ///
-/// The actual implementations are `ZipCommand` (uses the system command zip) or
-/// `ZipLibrary` (uses the [Rust zip library](https://crates.io/crates/zip)).
+/// The actual implementations are `ZipCommand` (uses the system command zip),
+/// `ZipLibrary` (uses the [Rust zip library](https://crates.io/crates/zip)),
+/// `ZipStream` (writes the archive directly to a writer), `ZipDirectory` (writes
+/// an unpacked EPUB to a directory) or `ZipMemory` (keeps the files in memory).
 pub trait Zip {
     /// Write the source content to a file in the archive
     fn write_file<P: AsRef<Path>, R: Read>(&mut self, file: P, content: R) -> Result<()>;
 
+    /// Write the source content to a file in the archive, overriding the default compression
+    fn write_file_compressed<P: AsRef<Path>, R: Read>(
+        &mut self,
+        file: P,
+        content: R,
+        compression: Compression,
+    ) -> Result<()>;
+
     /// Generate the ZIP file
     fn generate<W: Write>(self, _: W) -> Result<()>;
+
+    /// Use a fixed modification time and fixed permissions for the files written
+    /// after this call, so that identical input produces a byte-identical archive.
+    fn set_timestamp(&mut self, timestamp: chrono::DateTime<chrono::Utc>);
+}
+
+/// Asynchronous counterpart of [`Zip`], for implementations that can write the
+/// generated file to an asynchronous writer.
+///
+/// It is currently implemented by `ZipLibrary` and `ZipMemory`, which keep the archive
+/// in memory until it is written.
+#[cfg(feature = "async")]
+pub trait AsyncZip: Zip {
+    /// Generate the ZIP file, writing it asynchronously
+    fn generate_async<W: tokio::io::AsyncWrite + Unpin>(
+        self,
+        to: W,
+    ) -> impl std::future::Future<Output = Result<()>>;
+}
+
+/// Makes sure a path stays inside the archive, for implementations writing files
+/// to a directory.
+pub(crate) fn check_path(path: &Path) -> Result<()> {
+    let outside = path.components().any(|component| {
+        matches!(
+            component,
+            Component::ParentDir | Component::RootDir | Component::Prefix(_)
+        )
+    });
+    if outside {
+        return Err(crate::Error::InvalidPath(format!(
+            "file {} refers to a path outside the EPUB directory. This is verbotten!",
+            path.display()
+        )));
+    }
+    Ok(())
+}
+
+/// Unix permissions given to all files in reproducible mode
+pub(crate) const REPRODUCIBLE_PERMISSIONS: u32 = 0o644;
+
+/// How a file is compressed in the archive.
+///
+/// Already compressed formats such as JPEG or PNG images, MP3 files or WOFF2 fonts
+/// gain nothing by being deflated, so they can be stored instead.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum Compression {
+    /// Store the file as is
+    Stored,
+    /// Deflate the file, using the default level of the zip implementation
+    #[default]
+    Deflated,
+    /// Deflate the file with the given level, from 1 (fastest) to 9 (smallest)
+    ///
+    /// Other levels are rejected with `Error::InvalidCompressionLevel` when the file is
+    /// written.
+    DeflatedLevel(u8),
+}
+
+impl Compression {
+    /// Valid levels for `DeflatedLevel`, the same for all backends
+    const LEVELS: std::ops::RangeInclusive<u8> = 1..=9;
+
+    /// Returns the compression unchanged if its level is valid, or an error otherwise
+    pub(crate) fn validate(self) -> Result<Compression> {
+        match self {
+            Compression::DeflatedLevel(level) if !Self::LEVELS.contains(&level) => {
+                Err(crate::Error::InvalidCompressionLevel(level))
+            }
+            _ => Ok(self),
+        }
+    }
 }
@@ -2,11 +2,12 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with
 // this file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
+use crate::zip::check_path;
+use crate::zip::Compression;
 use crate::zip::Zip;
+use crate::zip_directory::write_to_dir;
 use crate::Result;
 
-use std::fs;
-use std::fs::DirBuilder;
 use std::fs::File;
 use std::io;
 use std::io::Read;
@@ -28,7 +29,9 @@ use std::process::Command;
 pub struct ZipCommand {
     command: String,
     temp_dir: tempfile::TempDir,
-    files: Vec<PathBuf>,
+    files: Vec<(PathBuf, Compression)>,
+    compression: Compression,
+    timestamp: Option<chrono::DateTime<chrono::Utc>>,
 }
 
 impl ZipCommand {
@@ -42,6 +45,8 @@ impl ZipCommand {
             command: String::from("zip"),
             temp_dir,
             files: vec![],
+            compression: Compression::Deflated,
+            timestamp: None,
         };
         Ok(zip)
     }
@@ -59,6 +64,8 @@ impl ZipCommand {
             command: String::from("zip"),
             temp_dir,
             files: vec![],
+            compression: Compression::Deflated,
+            timestamp: None,
         };
         Ok(zip)
     }
@@ -69,6 +76,13 @@ impl ZipCommand {
         self
     }
 
+    /// Set the compression used for files, unless it is overriden when adding them
+    /// (default: `Compression::Deflated`, which uses the best compression level)
+    pub fn compression(&mut self, compression: Compression) -> &mut Self {
+        self.compression = compression;
+        self
+    }
+
     /// Test that zip command works correctly (i.e program is installed)
     pub fn test(&self) -> Result<()> {
         let output = Command::new(&self.command)
@@ -90,62 +104,51 @@ impl ZipCommand {
     }
 
     /// Adds a file to the temporary directory
-    fn add_to_tmp_dir<P: AsRef<Path>, R: Read>(&mut self, path: P, mut content: R) -> Result<()> {
-        let dest_file = self.temp_dir.path().join(path.as_ref());
-        let dest_dir = dest_file.parent().unwrap();
-        if fs::metadata(dest_dir).is_err() {
-            // dir does not exist, create it
-            DirBuilder::new()
-                .recursive(true)
-                .create(dest_dir)
-                .map_err(|e| crate::Error::IoError {
-                    msg: format!(
-                        "could not create temporary directory in {path}",
-                        path = dest_dir.display()
-                    ),
-                    cause: e,
-                })?;
-        }
+    fn add_to_tmp_dir<P: AsRef<Path>, R: Read>(&mut self, path: P, content: R) -> Result<()> {
+        // In reproducible mode, the zip command takes timestamps and permissions
+        // from the filesystem
+        write_to_dir(self.temp_dir.path(), path.as_ref(), content, self.timestamp)
+    }
 
-        let mut f = File::create(&dest_file).map_err(|e| crate::Error::IoError {
-            msg: format!(
-                "could not write to temporary file {file}",
-                file = path.as_ref().display()
-            ),
-            cause: e,
-        })?;
-        io::copy(&mut content, &mut f).map_err(|e| crate::Error::IoError {
-            msg: format!(
-                "could not write to temporary file {file}",
-                file = path.as_ref().display()
-            ),
-            cause: e,
-        })?;
-        Ok(())
+    /// Creates a zip command running in the temporary directory
+    ///
+    /// In reproducible mode, extra file attributes (such as access times and owners)
+    /// are not stored, and timestamps are interpreted as UTC.
+    fn zip_command(&self) -> Command {
+        let mut command = Command::new(&self.command);
+        command.current_dir(self.temp_dir.path());
+        if self.timestamp.is_some() {
+            command.arg("-X").env("TZ", "UTC");
+        }
+        command
     }
 }
 
 impl Zip for ZipCommand {
     fn write_file<P: AsRef<Path>, R: Read>(&mut self, path: P, content: R) -> Result<()> {
+        self.write_file_compressed(path, content, self.compression)
+    }
+
+    fn write_file_compressed<P: AsRef<Path>, R: Read>(
+        &mut self,
+        path: P,
+        content: R,
+        compression: Compression,
+    ) -> Result<()> {
         let path = path.as_ref();
-        if path.starts_with("..") || path.is_absolute() {
-            return Err(crate::Error::InvalidPath(format!(
-                "file {} refers to a path outside the temporary directory. This is \
-                   verbotten!",
-                path.display()
-            )));
-        }
+        check_path(path)?;
+        let compression = compression.validate()?;
 
         self.add_to_tmp_dir(path, content)?;
-        self.files.push(path.to_path_buf());
+        self.files.push((path.to_path_buf(), compression));
         Ok(())
     }
 
     fn generate<W: Write>(mut self, mut to: W) -> Result<()> {
         // First, add mimetype and don't compress it
         self.add_to_tmp_dir("mimetype", b"application/epub+zip".as_ref())?;
-        let output = Command::new(&self.command)
-            .current_dir(self.temp_dir.path())
+        let output = self
+            .zip_command()
             .arg("-X0")
             .arg("output.epub")
             .arg("mimetype")
@@ -164,40 +167,50 @@ impl Zip for ZipCommand {
             )));
         }
 
-        let mut command = Command::new(&self.command);
-        command
-            .current_dir(self.temp_dir.path())
-            .arg("-9")
-            .arg("output.epub");
-        for file in &self.files {
-            command.arg(format!("{}", file.display()));
-        }
+        // Then add the other files, calling zip once for each run of files sharing
+        // the same compression so their order is kept
+        for files in self.files.chunk_by(|(_, a), (_, b)| a == b) {
+            let level = match files[0].1 {
+                Compression::Stored => String::from("-0"),
+                Compression::Deflated => String::from("-9"),
+                Compression::DeflatedLevel(level) => format!("-{level}"),
+            };
+            let mut command = self.zip_command();
+            command.arg(level).arg("output.epub");
+            for (file, _) in files {
+                command.arg(format!("{}", file.display()));
+            }
 
-        let output = command.output().map_err(|e| {
-            crate::Error::ZipCommandError(format!(
-                "failed to run command {name}: {e:?}",
-                name = self.command
-            ))
-        })?;
-        if output.status.success() {
-            let mut f = File::open(self.temp_dir.path().join("output.epub")).map_err(|e| {
-                crate::Error::IoError {
-                    msg: "error reading temporary epub file".to_string(),
-                    cause: e,
-                }
-            })?;
-            io::copy(&mut f, &mut to).map_err(|e| crate::Error::IoError {
-                msg: "error writing result of the zip command".to_string(),
-                cause: e,
+            let output = command.output().map_err(|e| {
+                crate::Error::ZipCommandError(format!(
+                    "failed to run command {name}: {e:?}",
+                    name = self.command
+                ))
             })?;
-            Ok(())
-        } else {
-            Err(crate::Error::ZipCommandError(format!(
-                "command {name} didn't return successfully: {output}",
-                name = self.command,
-                output = String::from_utf8_lossy(&output.stderr)
-            )))
+            if !output.status.success() {
+                return Err(crate::Error::ZipCommandError(format!(
+                    "command {name} didn't return successfully: {output}",
+                    name = self.command,
+                    output = String::from_utf8_lossy(&output.stderr)
+                )));
+            }
         }
+
+        let mut f = File::open(self.temp_dir.path().join("output.epub")).map_err(|e| {
+            crate::Error::IoError {
+                msg: "error reading temporary epub file".to_string(),
+                cause: e,
+            }
+        })?;
+        io::copy(&mut f, &mut to).map_err(|e| crate::Error::IoError {
+            msg: "error writing result of the zip command".to_string(),
+            cause: e,
+        })?;
+        Ok(())
+    }
+
+    fn set_timestamp(&mut self, timestamp: chrono::DateTime<chrono::Utc>) {
+        self.timestamp = Some(timestamp);
     }
 }
 
@@ -220,3 +233,62 @@ fn zip_not_ok() {
     let res = command.test();
     assert!(res.is_err());
 }
+
+#[cfg(feature = "libzip")]
+#[test]
+fn zip_reproducible() {
+    let timestamp = chrono::DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+    let build = || {
+        let mut command = ZipCommand::new().unwrap();
+        command.set_timestamp(timestamp);
+        command
+            .write_file("OEBPS/content.opf", b"content".as_ref())
+            .unwrap();
+        let mut output = vec![];
+        command.generate(&mut output).unwrap();
+        output
+    };
+    // Files get the given timestamp, whenever they are written
+    let output = build();
+    assert_eq!(output, build());
+    let expected = libzip::DateTime::from_date_and_time(2023, 11, 14, 22, 13, 20).unwrap();
+    let mut archive = libzip::ZipArchive::new(std::io::Cursor::new(output)).unwrap();
+    for i in 0..archive.len() {
+        assert_eq!(archive.by_index(i).unwrap().last_modified(), Some(expected));
+    }
+}
+
+#[cfg(feature = "libzip")]
+#[test]
+fn zip_compression() {
+    let mut command = ZipCommand::new().unwrap();
+    command
+        .write_file("OEBPS/a.xhtml", "a".repeat(100).as_bytes())
+        .unwrap();
+    command
+        .write_file_compressed(
+            "OEBPS/b.png",
+            "b".repeat(100).as_bytes(),
+            Compression::Stored,
+        )
+        .unwrap();
+    command
+        .write_file("OEBPS/c.xhtml", "c".repeat(100).as_bytes())
+        .unwrap();
+    let mut output = vec![];
+    command.generate(&mut output).unwrap();
+
+    let mut archive = libzip::ZipArchive::new(io::Cursor::new(output)).unwrap();
+    let names: Vec<_> = archive.file_names().collect();
+    assert_eq!(
+        names,
+        ["mimetype", "OEBPS/a.xhtml", "OEBPS/b.png", "OEBPS/c.xhtml"]
+    );
+    let compression =
+        |archive: &mut libzip::ZipArchive<_>, name| archive.by_name(name).unwrap().compression();
+    use libzip::CompressionMethod::*;
+    assert_eq!(compression(&mut archive, "mimetype"), Stored);
+    assert_eq!(compression(&mut archive, "OEBPS/a.xhtml"), Deflated);
+    assert_eq!(compression(&mut archive, "OEBPS/b.png"), Stored);
+    assert_eq!(compression(&mut archive, "OEBPS/c.xhtml"), Deflated);
+}
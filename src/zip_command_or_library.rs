@@ -2,6 +2,7 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with
 // this file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
+use crate::zip::Compression;
 use crate::zip::Zip;
 use crate::Result;
 use crate::ZipCommand;
@@ -14,6 +15,7 @@ use std::path::Path;
 /// Wrapper around either a ZipCommand or a ZipLibrary
 ///
 /// Allows to create an EpubBuilder that can decide at runtime which to use.
+#[allow(clippy::large_enum_variant)]
 pub enum ZipCommandOrLibrary {
     /// Command variant
     Command(ZipCommand),
@@ -29,12 +31,35 @@ impl Zip for ZipCommandOrLibrary {
         }
     }
 
+    fn write_file_compressed<P: AsRef<Path>, R: Read>(
+        &mut self,
+        path: P,
+        content: R,
+        compression: Compression,
+    ) -> Result<()> {
+        match self {
+            ZipCommandOrLibrary::Command(ref mut command) => {
+                command.write_file_compressed(path, content, compression)
+            }
+            ZipCommandOrLibrary::Library(ref mut library) => {
+                library.write_file_compressed(path, content, compression)
+            }
+        }
+    }
+
     fn generate<W: Write>(self, to: W) -> Result<()> {
         match self {
             ZipCommandOrLibrary::Command(command) => command.generate(to),
             ZipCommandOrLibrary::Library(library) => library.generate(to),
         }
     }
+
+    fn set_timestamp(&mut self, timestamp: chrono::DateTime<chrono::Utc>) {
+        match self {
+            ZipCommandOrLibrary::Command(ref mut command) => command.set_timestamp(timestamp),
+            ZipCommandOrLibrary::Library(ref mut library) => library.set_timestamp(timestamp),
+        }
+    }
 }
 
 impl ZipCommandOrLibrary {
@@ -0,0 +1,204 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with
+// this file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use crate::zip::check_path;
+use crate::zip::Compression;
+use crate::zip::Zip;
+#[cfg(unix)]
+use crate::zip::REPRODUCIBLE_PERMISSIONS;
+use crate::Result;
+
+use std::fs;
+use std::fs::DirBuilder;
+use std::fs::File;
+use std::io;
+use std::io::Read;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// Write files to a directory instead of an archive.
+///
+/// This produces an "unpacked" (or "exploded") EPUB, which is useful for debugging or
+/// for tools accepting this format. The directory is created if it doesn't exist.
+///
+/// Nothing is zipped, so the writer passed to the `generate` method of
+/// [`EpubBuilder`](struct.EpubBuilder.html) only receives a summary: the list of
+/// the files that were written, one per line.
+///
+/// Note that these takes care of adding the mimetype, it should not be added manually.
+///
+/// # Example
+///
+/// ```no_run
+/// use epub_builder::{EpubBuilder, ZipDirectory};
+/// use std::io;
+///
+/// let builder = EpubBuilder::new(ZipDirectory::new("book").unwrap()).unwrap();
+/// // Write the EPUB in the `book` directory, and print the list of files
+/// builder.generate(io::stdout()).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct ZipDirectory {
+    path: PathBuf,
+    files: Vec<PathBuf>,
+    timestamp: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl ZipDirectory {
+    /// Creates a new ZipDirectory, writing files in `path`.
+    pub fn new<P: AsRef<Path>>(path: P) -> Result<ZipDirectory> {
+        let path = path.as_ref();
+        DirBuilder::new()
+            .recursive(true)
+            .create(path)
+            .map_err(|e| crate::Error::IoError {
+                msg: format!("could not create directory {path}", path = path.display()),
+                cause: e,
+            })?;
+        Ok(ZipDirectory {
+            path: path.to_path_buf(),
+            files: vec![],
+            timestamp: None,
+        })
+    }
+}
+
+impl Zip for ZipDirectory {
+    fn write_file<P: AsRef<Path>, R: Read>(&mut self, path: P, content: R) -> Result<()> {
+        let path = path.as_ref();
+        check_path(path)?;
+
+        write_to_dir(&self.path, path, content, self.timestamp)?;
+        self.files.push(path.to_path_buf());
+        Ok(())
+    }
+
+    fn write_file_compressed<P: AsRef<Path>, R: Read>(
+        &mut self,
+        path: P,
+        content: R,
+        compression: Compression,
+    ) -> Result<()> {
+        // Files are not compressed, but invalid levels are rejected as in other backends
+        compression.validate()?;
+        self.write_file(path, content)
+    }
+
+    fn generate<W: Write>(self, mut to: W) -> Result<()> {
+        write_to_dir(
+            &self.path,
+            Path::new("mimetype"),
+            b"application/epub+zip".as_ref(),
+            self.timestamp,
+        )?;
+        let mut summary = String::from("mimetype\n");
+        for file in &self.files {
+            summary.push_str(&format!("{}\n", file.display()));
+        }
+        to.write_all(summary.as_bytes())
+            .map_err(|e| crate::Error::IoError {
+                msg: "error writing summary of the EPUB directory".to_string(),
+                cause: e,
+            })?;
+        Ok(())
+    }
+
+    fn set_timestamp(&mut self, timestamp: chrono::DateTime<chrono::Utc>) {
+        self.timestamp = Some(timestamp);
+    }
+}
+
+/// Writes a file in `dir`, creating its parent directories if needed.
+///
+/// If `timestamp` is set, it is used as the modification time of the file, which
+/// also gets fixed permissions.
+pub(crate) fn write_to_dir<R: Read>(
+    dir: &Path,
+    path: &Path,
+    mut content: R,
+    timestamp: Option<chrono::DateTime<chrono::Utc>>,
+) -> Result<()> {
+    let dest_file = dir.join(path);
+    let dest_dir = dest_file.parent().unwrap();
+    if fs::metadata(dest_dir).is_err() {
+        // dir does not exist, create it
+        DirBuilder::new()
+            .recursive(true)
+            .create(dest_dir)
+            .map_err(|e| crate::Error::IoError {
+                msg: format!(
+                    "could not create directory {path}",
+                    path = dest_dir.display()
+                ),
+                cause: e,
+            })?;
+    }
+
+    let mut f = File::create(&dest_file).map_err(|e| crate::Error::IoError {
+        msg: format!("could not write to file {file}", file = path.display()),
+        cause: e,
+    })?;
+    io::copy(&mut content, &mut f).map_err(|e| crate::Error::IoError {
+        msg: format!("could not write to file {file}", file = path.display()),
+        cause: e,
+    })?;
+    if let Some(timestamp) = timestamp {
+        f.set_modified(timestamp.into())
+            .map_err(|e| crate::Error::IoError {
+                msg: format!(
+                    "could not set modification time of file {file}",
+                    file = path.display()
+                ),
+                cause: e,
+            })?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            f.set_permissions(fs::Permissions::from_mode(REPRODUCIBLE_PERMISSIONS))
+                .map_err(|e| crate::Error::IoError {
+                    msg: format!(
+                        "could not set permissions of file {file}",
+                        file = path.display()
+                    ),
+                    cause: e,
+                })?;
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn directory_output() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let mut zip = ZipDirectory::new(dir.path().join("book")).unwrap();
+    zip.write_file("OEBPS/chapter.xhtml", b"Chapter".as_ref())
+        .unwrap();
+    let mut summary = vec![];
+    zip.generate(&mut summary).unwrap();
+
+    assert_eq!(
+        fs::read_to_string(dir.path().join("book/mimetype")).unwrap(),
+        "application/epub+zip"
+    );
+    assert_eq!(
+        fs::read_to_string(dir.path().join("book/OEBPS/chapter.xhtml")).unwrap(),
+        "Chapter"
+    );
+    assert_eq!(
+        String::from_utf8(summary).unwrap(),
+        "mimetype\nOEBPS/chapter.xhtml\n"
+    );
+}
+
+#[test]
+fn directory_path_outside() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let mut zip = ZipDirectory::new(dir.path()).unwrap();
+    assert!(zip.write_file("../chapter.xhtml", b"".as_ref()).is_err());
+    assert!(zip
+        .write_file("OEBPS/../../chapter.xhtml", b"".as_ref())
+        .is_err());
+    assert!(zip.write_file("/tmp/chapter.xhtml", b"".as_ref()).is_err());
+}
@@ -2,7 +2,9 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with
 // this file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
+use crate::zip::Compression;
 use crate::zip::Zip;
+use crate::zip::REPRODUCIBLE_PERMISSIONS;
 
 use std::fmt;
 use std::io;
@@ -12,6 +14,7 @@ use std::io::Write;
 use std::path::Path;
 
 use crate::Result;
+use libzip::write::SimpleFileOptions;
 use libzip::CompressionMethod;
 use libzip::ZipWriter;
 
@@ -25,6 +28,9 @@ use libzip::ZipWriter;
 /// should not be added manually.
 pub struct ZipLibrary {
     writer: ZipWriter<Cursor<Vec<u8>>>,
+    options: SimpleFileOptions,
+    compression: Compression,
+    mimetype: bool,
 }
 
 impl fmt::Debug for ZipLibrary {
@@ -36,33 +42,122 @@ impl fmt::Debug for ZipLibrary {
 impl ZipLibrary {
     /// Creates a new wrapper for zip library
     ///
-    /// Also add mimetype at the beginning of the EPUB file.
+    /// The mimetype will be added at the beginning of the EPUB file.
     pub fn new() -> Result<ZipLibrary> {
         let mut writer = ZipWriter::new(Cursor::new(vec![]));
         writer.set_comment(""); // Fix issues with some readers
-        writer.start_file(
+
+        Ok(ZipLibrary {
+            writer,
+            options: SimpleFileOptions::default(),
+            compression: Compression::default(),
+            mimetype: false,
+        })
+    }
+
+    /// Set the compression used for files, unless it is overriden when adding them
+    /// (default: `Compression::Deflated`)
+    pub fn compression(&mut self, compression: Compression) -> &mut Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Adds the mimetype at the beginning of the EPUB file, if it isn't there yet.
+    ///
+    /// This is delayed until the first file is written so that it gets the same
+    /// timestamp as the others in reproducible mode.
+    fn write_mimetype(&mut self) -> Result<()> {
+        if self.mimetype {
+            return Ok(());
+        }
+        self.writer.start_file(
             "mimetype",
-            libzip::write::SimpleFileOptions::default().compression_method(CompressionMethod::Stored),
+            self.options.compression_method(CompressionMethod::Stored),
         )?;
-        writer
-            .write(b"application/epub+zip")
+        self.writer
+            .write_all(b"application/epub+zip")
             .map_err(|e| crate::Error::IoError {
                 msg: "could not write mimetype in epub".to_string(),
                 cause: e,
             })?;
+        self.mimetype = true;
+        Ok(())
+    }
 
-        Ok(ZipLibrary { writer })
+    /// Finishes the archive and returns its content
+    fn finish(mut self) -> Result<Vec<u8>> {
+        self.write_mimetype()?;
+        let cursor = self
+            .writer
+            .finish()
+            .map_err(|e| crate::Error::ZipErrorWithMessage {
+                msg: "error writing zip file".to_string(),
+                cause: e,
+            })?;
+        Ok(cursor.into_inner())
     }
 }
 
+#[cfg(feature = "async")]
+impl crate::zip::AsyncZip for ZipLibrary {
+    async fn generate_async<W: tokio::io::AsyncWrite + Unpin>(self, mut to: W) -> Result<()> {
+        use tokio::io::AsyncWriteExt;
+
+        let bytes = self.finish()?;
+        let result = match to.write_all(bytes.as_ref()).await {
+            Ok(()) => to.flush().await,
+            Err(e) => Err(e),
+        };
+        result.map_err(|e| crate::Error::IoError {
+            msg: "error writing to file".to_string(),
+            cause: e,
+        })?;
+        Ok(())
+    }
+}
+
+/// Converts a timestamp to the format used in zip files, which can't represent
+/// dates before 1980 or after 2107.
+pub(crate) fn zip_date_time(timestamp: chrono::DateTime<chrono::Utc>) -> libzip::DateTime {
+    use chrono::{Datelike, Timelike};
+
+    libzip::DateTime::from_date_and_time(
+        timestamp.year().clamp(1980, 2107) as u16,
+        timestamp.month() as u8,
+        timestamp.day() as u8,
+        timestamp.hour() as u8,
+        timestamp.minute() as u8,
+        timestamp.second() as u8,
+    )
+    .unwrap_or_default()
+}
+
 impl Zip for ZipLibrary {
-    fn write_file<P: AsRef<Path>, R: Read>(&mut self, path: P, mut content: R) -> Result<()> {
+    fn write_file<P: AsRef<Path>, R: Read>(&mut self, path: P, content: R) -> Result<()> {
+        self.write_file_compressed(path, content, self.compression)
+    }
+
+    fn write_file_compressed<P: AsRef<Path>, R: Read>(
+        &mut self,
+        path: P,
+        mut content: R,
+        compression: Compression,
+    ) -> Result<()> {
+        let compression = compression.validate()?;
+        self.write_mimetype()?;
         let mut file = format!("{}", path.as_ref().display());
         if cfg!(target_os = "windows") {
             // Path names should not use backspaces in zip files
             file = file.replace('\\', "/");
         }
-        let options = libzip::write::SimpleFileOptions::default();
+        let options = match compression {
+            Compression::Stored => self.options.compression_method(CompressionMethod::Stored),
+            Compression::Deflated => self.options.compression_method(CompressionMethod::Deflated),
+            Compression::DeflatedLevel(level) => self
+                .options
+                .compression_method(CompressionMethod::Deflated)
+                .compression_level(Some(level.into())),
+        };
         self.writer.start_file(file.clone(), options).map_err(|e| {
             crate::Error::ZipErrorWithMessage {
                 msg: format!("could not create file '{}' in epub", file),
@@ -77,14 +172,7 @@ impl Zip for ZipLibrary {
     }
 
     fn generate<W: Write>(self, mut to: W) -> Result<()> {
-        let cursor = self
-            .writer
-            .finish()
-            .map_err(|e| crate::Error::ZipErrorWithMessage {
-                msg: "error writing zip file".to_string(),
-                cause: e,
-            })?;
-        let bytes = cursor.into_inner();
+        let bytes = self.finish()?;
         to.write_all(bytes.as_ref())
             .map_err(|e| crate::Error::IoError {
                 msg: "error writing to file".to_string(),
@@ -92,4 +180,11 @@ impl Zip for ZipLibrary {
             })?;
         Ok(())
     }
+
+    fn set_timestamp(&mut self, timestamp: chrono::DateTime<chrono::Utc>) {
+        self.options = self
+            .options
+            .last_modified_time(zip_date_time(timestamp))
+            .unix_permissions(REPRODUCIBLE_PERMISSIONS);
+    }
 }
@@ -0,0 +1,167 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with
+// this file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use crate::zip::check_path;
+use crate::zip::Compression;
+use crate::zip::Zip;
+use crate::Result;
+use crate::ZipStream;
+
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// Keep files in memory instead of zipping them.
+///
+/// This is mostly useful for tests: combined with the `into_zip` method of
+/// [`EpubBuilder`](struct.EpubBuilder.html), it allows to look at each generated file
+/// (`OEBPS/content.opf`, `OEBPS/nav.xhtml`, chapters, ...) without unzipping the EPUB.
+///
+/// It can still be turned into a real zip file with `to_zip`, which is what `generate`
+/// does.
+///
+/// # Example
+///
+/// ```
+/// use epub_builder::{EpubBuilder, ZipMemory};
+///
+/// let mut builder = EpubBuilder::new(ZipMemory::new()).unwrap();
+/// builder.metadata("title", "In Memory").unwrap();
+/// let zip = builder.into_zip().unwrap();
+/// let opf = zip.get_str("OEBPS/content.opf").unwrap();
+/// assert!(opf.contains("<dc:title>In Memory</dc:title>"));
+/// ```
+#[derive(Debug, Default)]
+pub struct ZipMemory {
+    files: BTreeMap<PathBuf, Vec<u8>>,
+    compression: BTreeMap<PathBuf, Compression>,
+    timestamp: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl ZipMemory {
+    /// Creates a new, empty, ZipMemory
+    pub fn new() -> ZipMemory {
+        ZipMemory::default()
+    }
+
+    /// Returns the paths of all the files, in alphabetical order
+    ///
+    /// Note that the mimetype is only added when writing a zip file, so it is not listed.
+    pub fn files(&self) -> impl Iterator<Item = &Path> {
+        self.files.keys().map(PathBuf::as_path)
+    }
+
+    /// Returns the content of a file, e.g. `OEBPS/content.opf`
+    pub fn get<P: AsRef<Path>>(&self, path: P) -> Option<&[u8]> {
+        self.files.get(path.as_ref()).map(Vec::as_slice)
+    }
+
+    /// Returns the content of a file as a string, or `None` if it doesn't exist or
+    /// isn't valid UTF-8
+    pub fn get_str<P: AsRef<Path>>(&self, path: P) -> Option<&str> {
+        self.get(path)
+            .and_then(|bytes| std::str::from_utf8(bytes).ok())
+    }
+
+    /// Returns all the files
+    pub fn into_inner(self) -> BTreeMap<PathBuf, Vec<u8>> {
+        self.files
+    }
+
+    /// Writes the files to a zip archive
+    pub fn to_zip<W: Write>(&self, to: W) -> Result<()> {
+        let mut zip = ZipStream::new(to)?;
+        if let Some(timestamp) = self.timestamp {
+            zip.set_timestamp(timestamp);
+        }
+        for (path, content) in &self.files {
+            match self.compression.get(path) {
+                Some(compression) => {
+                    zip.write_file_compressed(path, content.as_slice(), *compression)?
+                }
+                None => zip.write_file(path, content.as_slice())?,
+            }
+        }
+        zip.generate(std::io::sink())
+    }
+}
+
+impl Zip for ZipMemory {
+    fn write_file<P: AsRef<Path>, R: Read>(&mut self, path: P, mut content: R) -> Result<()> {
+        check_path(path.as_ref())?;
+        let mut bytes = vec![];
+        content
+            .read_to_end(&mut bytes)
+            .map_err(|e| crate::Error::IoError {
+                msg: format!("could not read file '{}'", path.as_ref().display()),
+                cause: e,
+            })?;
+        self.compression.remove(path.as_ref());
+        self.files.insert(path.as_ref().to_path_buf(), bytes);
+        Ok(())
+    }
+
+    fn write_file_compressed<P: AsRef<Path>, R: Read>(
+        &mut self,
+        path: P,
+        content: R,
+        compression: Compression,
+    ) -> Result<()> {
+        let compression = compression.validate()?;
+        self.write_file(path.as_ref(), content)?;
+        self.compression
+            .insert(path.as_ref().to_path_buf(), compression);
+        Ok(())
+    }
+
+    fn generate<W: Write>(self, to: W) -> Result<()> {
+        self.to_zip(to)
+    }
+
+    fn set_timestamp(&mut self, timestamp: chrono::DateTime<chrono::Utc>) {
+        self.timestamp = Some(timestamp);
+    }
+}
+
+#[cfg(feature = "async")]
+impl crate::zip::AsyncZip for ZipMemory {
+    async fn generate_async<W: tokio::io::AsyncWrite + Unpin>(self, mut to: W) -> Result<()> {
+        use tokio::io::AsyncWriteExt;
+
+        let mut bytes = vec![];
+        self.to_zip(&mut bytes)?;
+        let result = match to.write_all(bytes.as_ref()).await {
+            Ok(()) => to.flush().await,
+            Err(e) => Err(e),
+        };
+        result.map_err(|e| crate::Error::IoError {
+            msg: "error writing to file".to_string(),
+            cause: e,
+        })?;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "libzip")]
+#[test]
+fn memory_to_zip() {
+    let mut zip = ZipMemory::new();
+    zip.write_file("OEBPS/chapter.xhtml", b"Chapter".as_ref())
+        .unwrap();
+    assert_eq!(zip.get_str("OEBPS/chapter.xhtml"), Some("Chapter"));
+    assert_eq!(zip.get("OEBPS/missing.xhtml"), None);
+    assert!(matches!(
+        zip.write_file("../outside.xhtml", b"".as_ref()),
+        Err(crate::Error::InvalidPath(_))
+    ));
+    assert!(zip.write_file("/outside.xhtml", b"".as_ref()).is_err());
+
+    let mut output = vec![];
+    zip.generate(&mut output).unwrap();
+    let archive = libzip::ZipArchive::new(std::io::Cursor::new(output)).unwrap();
+    let names: Vec<_> = archive.file_names().collect();
+    assert_eq!(names, ["mimetype", "OEBPS/chapter.xhtml"]);
+}
@@ -0,0 +1,600 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with
+// this file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use crate::zip::check_path;
+use crate::zip::Compression;
+use crate::zip::Zip;
+use crate::zip::REPRODUCIBLE_PERMISSIONS;
+use crate::Result;
+
+use std::fmt;
+use std::io;
+use std::io::Read;
+use std::io::Seek;
+use std::io::SeekFrom;
+use std::io::Write;
+use std::path::Path;
+
+use flate2::write::DeflateEncoder;
+
+const LOCAL_HEADER_SIGNATURE: u32 = 0x0403_4b50;
+const DATA_DESCRIPTOR_SIGNATURE: u32 = 0x0807_4b50;
+const CENTRAL_HEADER_SIGNATURE: u32 = 0x0201_4b50;
+const END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x0605_4b50;
+
+/// Version 2.0 of the zip specification, needed for deflate and data descriptors
+const ZIP_VERSION: u16 = 20;
+/// Sizes and CRC are written in a data descriptor after the content
+const FLAG_DATA_DESCRIPTOR: u16 = 1 << 3;
+/// File names are encoded in UTF-8
+const FLAG_UTF8: u16 = 1 << 11;
+
+const METHOD_STORED: u16 = 0;
+const METHOD_DEFLATED: u16 = 8;
+
+/// Zip files by streaming them directly to a writer.
+///
+/// Unlike [`ZipLibrary`](struct.ZipLibrary.html), which keeps the whole archive in memory
+/// until `generate` is called, or [`ZipCommand`](struct.ZipCommand.html), which writes
+/// everything to a temporary directory, each file is compressed and written to the
+/// underlying writer as soon as it is given to this backend. This makes it suitable for
+/// large books, as long as their content is added with a
+/// [`ContentSource`](enum.ContentSource.html) that doesn't keep it in memory.
+///
+/// The writer doesn't need to implement `Seek`: the size and checksum of each deflated file
+/// are written in a data descriptor after its content, so it can be a socket or `stdout`.
+/// Stored files are read in memory first, since some readers don't accept data descriptors
+/// for them. If the writer implements `Seek`, such as a `File`, `ZipStream::new_seekable`
+/// avoids both by going back to write the size and checksum in the header of each file.
+///
+/// The writer passed to the `generate` method of [`EpubBuilder`](struct.EpubBuilder.html)
+/// is not used: the archive is written to the writer given to `ZipStream::new`.
+///
+/// Note that these takes care of adding the mimetype (since it must not be deflated), it
+/// should not be added manually.
+///
+/// # Example
+///
+/// ```no_run
+/// use epub_builder::{EpubBuilder, ZipStream};
+/// use std::fs::File;
+/// use std::io;
+///
+/// let file = File::create("book.epub").unwrap();
+/// let builder = EpubBuilder::new(ZipStream::new_seekable(file).unwrap()).unwrap();
+/// // The EPUB has already been written to `book.epub`
+/// builder.generate(io::sink()).unwrap();
+/// ```
+pub struct ZipStream<W: Write> {
+    writer: CountingWriter<W>,
+    entries: Vec<Entry>,
+    time: u16,
+    date: u16,
+    permissions: Option<u32>,
+    compression: Compression,
+    mimetype: bool,
+    /// Seeks in the writer, if it implements `Seek`
+    seek: Option<fn(&mut W, u64) -> io::Result<()>>,
+    /// Position of the writer when the stream was created
+    start: u64,
+}
+
+/// Information about a file already written, needed for the central directory
+struct Entry {
+    name: String,
+    flags: u16,
+    method: u16,
+    time: u16,
+    date: u16,
+    crc: u32,
+    compressed_size: u32,
+    size: u32,
+    permissions: Option<u32>,
+    offset: u32,
+}
+
+/// Keeps track of the number of bytes written to the inner writer
+struct CountingWriter<W: Write> {
+    inner: W,
+    count: u64,
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.count += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<W: Write> fmt::Debug for ZipStream<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ZipStream")
+    }
+}
+
+impl<W: Write> ZipStream<W> {
+    /// Creates a new zip stream writing to `writer`
+    ///
+    /// The mimetype will be added at the beginning of the EPUB file.
+    pub fn new(writer: W) -> Result<ZipStream<W>> {
+        let (time, date) = dos_date_time(chrono::Utc::now());
+        Ok(ZipStream {
+            writer: CountingWriter {
+                inner: writer,
+                count: 0,
+            },
+            entries: vec![],
+            time,
+            date,
+            permissions: None,
+            compression: Compression::default(),
+            mimetype: false,
+            seek: None,
+            start: 0,
+        })
+    }
+
+    /// Set the compression used for files, unless it is overriden when adding them
+    /// (default: `Compression::Deflated`)
+    pub fn compression(&mut self, compression: Compression) -> &mut Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Adds the mimetype at the beginning of the EPUB file, if it isn't there yet.
+    ///
+    /// Its size is known in advance, so it is written without a data descriptor, which
+    /// some reading systems don't expect for this file.
+    fn write_mimetype(&mut self) -> Result<()> {
+        if self.mimetype {
+            return Ok(());
+        }
+        self.mimetype = true;
+        let content = b"application/epub+zip";
+        let mut entry = self.new_entry("mimetype", 0, METHOD_STORED)?;
+        entry.crc = crc32fast::hash(content);
+        entry.compressed_size = content.len() as u32;
+        entry.size = content.len() as u32;
+        self.write_local_header(&entry)
+            .and_then(|_| self.writer.write_all(content))
+            .map_err(|e| crate::Error::IoError {
+                msg: "could not write mimetype in epub".to_string(),
+                cause: e,
+            })?;
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    /// Writes the content of a file, deflated if there is a compression level, and returns
+    /// the checksum and size of the content
+    fn write_content<R: Read>(
+        &mut self,
+        file: &str,
+        content: &mut R,
+        level: Option<flate2::Compression>,
+    ) -> Result<(u32, u64)> {
+        let Some(level) = level else {
+            return copy_with_crc(content, &mut self.writer, file);
+        };
+        let mut encoder = DeflateEncoder::new(&mut self.writer, level);
+        let result = copy_with_crc(content, &mut encoder, file)?;
+        encoder.finish().map_err(|e| crate::Error::IoError {
+            msg: format!("could not write file '{}' in epub", file),
+            cause: e,
+        })?;
+        Ok(result)
+    }
+
+    fn new_entry(&self, name: &str, flags: u16, method: u16) -> Result<Entry> {
+        if self.entries.len() >= usize::from(u16::MAX) {
+            return Err(too_large(
+                name,
+                "too many files for a zip file without zip64",
+            ));
+        }
+        if u16::try_from(name.len()).is_err() {
+            return Err(too_large(name, "file name is too long for a zip file"));
+        }
+        Ok(Entry {
+            name: name.to_string(),
+            flags,
+            method,
+            time: self.time,
+            date: self.date,
+            crc: 0,
+            compressed_size: 0,
+            size: 0,
+            permissions: self.permissions,
+            offset: to_u32(self.writer.count, name)?,
+        })
+    }
+
+    fn write_local_header(&mut self, entry: &Entry) -> io::Result<()> {
+        let w = &mut self.writer;
+        write_u32(w, LOCAL_HEADER_SIGNATURE)?;
+        write_u16(w, ZIP_VERSION)?;
+        write_u16(w, entry.flags)?;
+        write_u16(w, entry.method)?;
+        write_u16(w, entry.time)?;
+        write_u16(w, entry.date)?;
+        write_u32(w, entry.crc)?;
+        write_u32(w, entry.compressed_size)?;
+        write_u32(w, entry.size)?;
+        write_u16(w, entry.name.len() as u16)?;
+        write_u16(w, 0)?; // extra field length
+        w.write_all(entry.name.as_bytes())
+    }
+
+    /// Writes a file without compressing it
+    ///
+    /// Data descriptors are not well supported for stored files, so the content is read
+    /// in memory first to write its size and checksum in the header.
+    fn write_stored<R: Read>(&mut self, file: &str, mut content: R) -> Result<()> {
+        let mut bytes = vec![];
+        content
+            .read_to_end(&mut bytes)
+            .map_err(|e| crate::Error::IoError {
+                msg: format!("could not write file '{}' in epub", file),
+                cause: e,
+            })?;
+        let mut entry = self.new_entry(file, FLAG_UTF8, METHOD_STORED)?;
+        entry.crc = crc32fast::hash(&bytes);
+        entry.size = to_u32(bytes.len() as u64, file)?;
+        entry.compressed_size = entry.size;
+        self.write_local_header(&entry)
+            .and_then(|_| self.writer.write_all(&bytes))
+            .map_err(|e| crate::Error::IoError {
+                msg: format!("could not write file '{}' in epub", file),
+                cause: e,
+            })?;
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    fn write_central_directory(&mut self) -> io::Result<()> {
+        let start = self.writer.count;
+        let w = &mut self.writer;
+        for entry in &self.entries {
+            let (version_made_by, external_attributes) = match entry.permissions {
+                // Upper byte 3: Unix, permissions in the high bits of the external attributes
+                Some(permissions) => ((3 << 8) | ZIP_VERSION, (0o100_000 | permissions) << 16),
+                None => (ZIP_VERSION, 0),
+            };
+            write_u32(w, CENTRAL_HEADER_SIGNATURE)?;
+            write_u16(w, version_made_by)?;
+            write_u16(w, ZIP_VERSION)?;
+            write_u16(w, entry.flags)?;
+            write_u16(w, entry.method)?;
+            write_u16(w, entry.time)?;
+            write_u16(w, entry.date)?;
+            write_u32(w, entry.crc)?;
+            write_u32(w, entry.compressed_size)?;
+            write_u32(w, entry.size)?;
+            write_u16(w, entry.name.len() as u16)?;
+            write_u16(w, 0)?; // extra field length
+            write_u16(w, 0)?; // comment length
+            write_u16(w, 0)?; // disk number
+            write_u16(w, 0)?; // internal attributes
+            write_u32(w, external_attributes)?;
+            write_u32(w, entry.offset)?;
+            w.write_all(entry.name.as_bytes())?;
+        }
+        let size = self.writer.count - start;
+        let (Ok(start), Ok(size), Ok(count)) = (
+            u32::try_from(start),
+            u32::try_from(size),
+            u16::try_from(self.entries.len()),
+        ) else {
+            return Err(io::Error::other(
+                "archive is too large for a zip file without zip64",
+            ));
+        };
+        let w = &mut self.writer;
+        write_u32(w, END_OF_CENTRAL_DIRECTORY_SIGNATURE)?;
+        write_u16(w, 0)?; // number of this disk
+        write_u16(w, 0)?; // disk where central directory starts
+        write_u16(w, count)?;
+        write_u16(w, count)?;
+        write_u32(w, size)?;
+        write_u32(w, start)?;
+        write_u16(w, 0)?; // comment length
+        w.flush()
+    }
+}
+
+impl<W: Write + Seek> ZipStream<W> {
+    /// Creates a new zip stream writing to a seekable `writer`, such as a `File`
+    ///
+    /// The size and checksum of each file are written in its header after its content,
+    /// instead of in a data descriptor, so no file needs to be read in memory.
+    pub fn new_seekable(mut writer: W) -> Result<ZipStream<W>> {
+        let start = writer.stream_position()?;
+        let mut zip = ZipStream::new(writer)?;
+        zip.seek = Some(seek_to::<W>);
+        zip.start = start;
+        Ok(zip)
+    }
+}
+
+impl<W: Write> Zip for ZipStream<W> {
+    fn write_file<P: AsRef<Path>, R: Read>(&mut self, path: P, content: R) -> Result<()> {
+        self.write_file_compressed(path, content, self.compression)
+    }
+
+    fn write_file_compressed<P: AsRef<Path>, R: Read>(
+        &mut self,
+        path: P,
+        mut content: R,
+        compression: Compression,
+    ) -> Result<()> {
+        self.write_mimetype()?;
+        check_path(path.as_ref())?;
+        let mut file = format!("{}", path.as_ref().display());
+        if cfg!(target_os = "windows") {
+            // Path names should not use backspaces in zip files
+            file = file.replace('\\', "/");
+        }
+        let level = match compression.validate()? {
+            Compression::Stored => None,
+            Compression::Deflated => Some(flate2::Compression::default()),
+            Compression::DeflatedLevel(level) => Some(flate2::Compression::new(level.into())),
+        };
+        let (flags, method) = match (self.seek, level) {
+            (None, None) => return self.write_stored(&file, content),
+            (None, Some(_)) => (FLAG_DATA_DESCRIPTOR | FLAG_UTF8, METHOD_DEFLATED),
+            (Some(_), None) => (FLAG_UTF8, METHOD_STORED),
+            (Some(_), Some(_)) => (FLAG_UTF8, METHOD_DEFLATED),
+        };
+        let mut entry = self.new_entry(&file, flags, method)?;
+        self.write_local_header(&entry)
+            .map_err(|e| crate::Error::IoError {
+                msg: format!("could not create file '{}' in epub", file),
+                cause: e,
+            })?;
+
+        let start = self.writer.count;
+        let (crc, size) = self.write_content(&file, &mut content, level)?;
+        entry.crc = crc;
+        entry.size = to_u32(size, &file)?;
+        entry.compressed_size = to_u32(self.writer.count - start, &file)?;
+        let result = match self.seek {
+            // Go back to the checksum in the local header, then to the end of the file
+            Some(seek) => {
+                let header = self.start + u64::from(entry.offset) + 14;
+                let end = self.start + self.writer.count;
+                let w = &mut self.writer.inner;
+                seek(w, header)
+                    .and_then(|_| write_u32(w, entry.crc))
+                    .and_then(|_| write_u32(w, entry.compressed_size))
+                    .and_then(|_| write_u32(w, entry.size))
+                    .and_then(|_| seek(w, end))
+            }
+            None => {
+                let w = &mut self.writer;
+                write_u32(w, DATA_DESCRIPTOR_SIGNATURE)
+                    .and_then(|_| write_u32(w, entry.crc))
+                    .and_then(|_| write_u32(w, entry.compressed_size))
+                    .and_then(|_| write_u32(w, entry.size))
+            }
+        };
+        result.map_err(|e| crate::Error::IoError {
+            msg: format!("could not write file '{}' in epub", file),
+            cause: e,
+        })?;
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    fn generate<W2: Write>(mut self, _: W2) -> Result<()> {
+        self.write_mimetype()?;
+        self.write_central_directory()
+            .map_err(|e| crate::Error::IoError {
+                msg: "error writing zip file".to_string(),
+                cause: e,
+            })?;
+        Ok(())
+    }
+
+    fn set_timestamp(&mut self, timestamp: chrono::DateTime<chrono::Utc>) {
+        (self.time, self.date) = dos_date_time(timestamp);
+        self.permissions = Some(REPRODUCIBLE_PERMISSIONS);
+    }
+}
+
+/// Converts a timestamp to the MS-DOS time and date stored in zip files, which can't
+/// represent dates before 1980 or after 2107.
+fn dos_date_time(timestamp: chrono::DateTime<chrono::Utc>) -> (u16, u16) {
+    use chrono::{Datelike, Timelike};
+
+    if timestamp.year() < 1980 {
+        return (0, (1 << 5) | 1);
+    }
+    let year = timestamp.year().min(2107) as u16;
+    let time = ((timestamp.hour() as u16) << 11)
+        | ((timestamp.minute() as u16) << 5)
+        | (timestamp.second() as u16 / 2);
+    let date = ((year - 1980) << 9) | ((timestamp.month() as u16) << 5) | timestamp.day() as u16;
+    (time, date)
+}
+
+fn seek_to<W: Seek>(writer: &mut W, position: u64) -> io::Result<()> {
+    writer.seek(SeekFrom::Start(position)).map(|_| ())
+}
+
+/// Copies `content` to `writer`, returning the checksum and size of the content
+fn copy_with_crc<R: Read, W: Write>(
+    content: &mut R,
+    writer: &mut W,
+    file: &str,
+) -> Result<(u32, u64)> {
+    let mut hasher = crc32fast::Hasher::new();
+    let mut size: u64 = 0;
+    let mut buffer = [0; 64 * 1024];
+    loop {
+        let n = match content.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => {
+                return Err(crate::Error::IoError {
+                    msg: format!("could not write file '{}' in epub", file),
+                    cause: e,
+                })
+            }
+        };
+        hasher.update(&buffer[..n]);
+        size += n as u64;
+        writer
+            .write_all(&buffer[..n])
+            .map_err(|e| crate::Error::IoError {
+                msg: format!("could not write file '{}' in epub", file),
+                cause: e,
+            })?;
+    }
+    Ok((hasher.finalize(), size))
+}
+
+/// Sizes and offsets must fit in 32 bits since zip64 is not supported
+fn to_u32(n: u64, file: &str) -> Result<u32> {
+    u32::try_from(n)
+        .map_err(|_| too_large(file, "archive is too large for a zip file without zip64"))
+}
+
+/// Error returned when `file` can't be written because of a limit of the zip format
+fn too_large(file: &str, cause: &str) -> crate::Error {
+    crate::Error::IoError {
+        msg: format!("could not write file '{}' in epub", file),
+        cause: io::Error::other(cause.to_string()),
+    }
+}
+
+fn write_u16<W: Write>(w: &mut W, n: u16) -> io::Result<()> {
+    w.write_all(&n.to_le_bytes())
+}
+
+fn write_u32<W: Write>(w: &mut W, n: u32) -> io::Result<()> {
+    w.write_all(&n.to_le_bytes())
+}
+
+#[cfg(feature = "libzip")]
+#[test]
+fn zip_stream_readable() {
+    let mut output = vec![];
+    let mut zip = ZipStream::new(&mut output).unwrap();
+    zip.write_file("META-INF/container.xml", b"container".as_ref())
+        .unwrap();
+    zip.write_file("OEBPS/chapter.xhtml", "Chapitre été".repeat(100).as_bytes())
+        .unwrap();
+    zip.write_file_compressed("OEBPS/image.png", b"PNG".as_ref(), Compression::Stored)
+        .unwrap();
+    zip.generate(io::sink()).unwrap();
+
+    let mut archive = libzip::ZipArchive::new(io::Cursor::new(output)).unwrap();
+    assert_eq!(archive.len(), 4);
+    let mut content = String::new();
+    let mut mimetype = archive.by_index(0).unwrap();
+    assert_eq!(mimetype.name(), "mimetype");
+    assert_eq!(mimetype.compression(), libzip::CompressionMethod::Stored);
+    mimetype.read_to_string(&mut content).unwrap();
+    assert_eq!(content, "application/epub+zip");
+    drop(mimetype);
+
+    content.clear();
+    archive
+        .by_name("OEBPS/chapter.xhtml")
+        .unwrap()
+        .read_to_string(&mut content)
+        .unwrap();
+    assert_eq!(content, "Chapitre été".repeat(100));
+
+    content.clear();
+    let mut image = archive.by_name("OEBPS/image.png").unwrap();
+    assert_eq!(image.compression(), libzip::CompressionMethod::Stored);
+    image.read_to_string(&mut content).unwrap();
+    assert_eq!(content, "PNG");
+}
+
+#[test]
+fn zip_stream_invalid_level() {
+    let mut zip = ZipStream::new(io::sink()).unwrap();
+    for level in [0, 10] {
+        let res = zip.write_file_compressed(
+            "OEBPS/chapter.xhtml",
+            b"chapter".as_ref(),
+            Compression::DeflatedLevel(level),
+        );
+        assert!(matches!(res, Err(crate::Error::InvalidCompressionLevel(l)) if l == level));
+    }
+    zip.write_file_compressed(
+        "OEBPS/chapter.xhtml",
+        b"chapter".as_ref(),
+        Compression::DeflatedLevel(1),
+    )
+    .unwrap();
+}
+
+#[test]
+fn zip_stream_limits() {
+    let mut zip = ZipStream::new(io::sink()).unwrap();
+    let name = "a".repeat(usize::from(u16::MAX) + 1);
+    assert!(zip.write_file(&name, b"".as_ref()).is_err());
+    for i in 1..u16::MAX {
+        zip.write_file(format!("{i}"), b"".as_ref()).unwrap();
+    }
+    assert!(zip.write_file("last", b"".as_ref()).is_err());
+}
+
+#[test]
+fn zip_stream_stored_without_data_descriptor() {
+    let mut output = vec![];
+    let mut zip = ZipStream::new(&mut output).unwrap();
+    zip.write_file_compressed("image.png", b"PNG".as_ref(), Compression::Stored)
+        .unwrap();
+    zip.generate(io::sink()).unwrap();
+
+    // The local header of the file comes right after the mimetype
+    let header = &output[30 + "mimetype".len() + "application/epub+zip".len()..];
+    assert_eq!(header[..4], LOCAL_HEADER_SIGNATURE.to_le_bytes());
+    assert_eq!(header[6..8], FLAG_UTF8.to_le_bytes());
+    assert_eq!(header[8..10], METHOD_STORED.to_le_bytes());
+    assert_eq!(header[14..18], crc32fast::hash(b"PNG").to_le_bytes());
+    assert_eq!(header[18..22], 3u32.to_le_bytes());
+    assert!(!output
+        .windows(4)
+        .any(|w| w == DATA_DESCRIPTOR_SIGNATURE.to_le_bytes()));
+}
+
+#[cfg(feature = "libzip")]
+#[test]
+fn zip_stream_seekable() {
+    let mut output = io::Cursor::new(b"prefix".to_vec());
+    output.seek(SeekFrom::End(0)).unwrap();
+    let mut zip = ZipStream::new_seekable(&mut output).unwrap();
+    zip.write_file("OEBPS/chapter.xhtml", "Chapitre été".repeat(100).as_bytes())
+        .unwrap();
+    zip.write_file_compressed("OEBPS/image.png", b"PNG".as_ref(), Compression::Stored)
+        .unwrap();
+    zip.generate(io::sink()).unwrap();
+
+    let output = output.into_inner();
+    assert!(!output
+        .windows(4)
+        .any(|w| w == DATA_DESCRIPTOR_SIGNATURE.to_le_bytes()));
+    let mut archive = libzip::ZipArchive::new(io::Cursor::new(&output[6..])).unwrap();
+    let mut content = String::new();
+    archive
+        .by_name("OEBPS/chapter.xhtml")
+        .unwrap()
+        .read_to_string(&mut content)
+        .unwrap();
+    assert_eq!(content, "Chapitre été".repeat(100));
+    let image = archive.by_name("OEBPS/image.png").unwrap();
+    assert_eq!(image.compression(), libzip::CompressionMethod::Stored);
+    assert_eq!(image.size(), 3);
+}